@@ -7,21 +7,28 @@ use std::io::BufWriter;
 use std::fs::File;
 use std::error::Error;
 use std::path;
+use std::collections::HashMap;
+use std::collections::BinaryHeap;
+use std::collections::VecDeque;
+use std::cmp::Ordering;
+use std::cmp::Reverse;
 use serde::Deserialize;
 use std::process;
 use itertools::Itertools;
-use csv;
 
 #[cfg(test)]
 mod tests {
 
-    /////////////////////////////////////////////////////////
-    // update to work anywhere, setting src depending on where we are?
-    /////////////////////////////////////////////////////////
-    const TESTDIR: &str = "/corexfs/schroedj/src/bio-anno-rs/test_files";
+    const TESTDIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_files");
     use super::*;
     use approx::assert_abs_diff_eq;
 
+    /// a path in the temporary directory that no other test, or concurrent
+    /// run of the test suite, writes to
+    fn unique_temp_path(name: &str) -> path::PathBuf {
+        std::env::temp_dir().join(format!("bio_anno_rs_{}_{}", std::process::id(), name))
+    }
+
     #[test]
     fn test_unify_widths() {
         let bgd = BEDGraphData::from_file(
@@ -116,7 +123,7 @@ mod tests {
         let bgd = BEDGraphData::from_file(
             &path::Path::new(TESTDIR).join("cov.bedgraph"),
         ).unwrap();
-        let cpm = bgd.get_cpm().unwrap();
+        let cpm = bgd.get_cpm(NanPolicy::Propagate).unwrap();
         let answer = vec![
             17133.54, 17133.54, 17133.54,
             159880.08, 214033.18, 124028.77,
@@ -137,13 +144,88 @@ mod tests {
             159880.08, 214033.18, 124028.77,
             307175.93, 99303.01, 44178.41,
         ];
-        bgd.to_cpm().unwrap();
+        bgd.to_cpm(NanPolicy::Propagate).unwrap();
         for (i,res) in answer.iter().enumerate() {
             assert_abs_diff_eq!(*res, bgd.data[i].score, epsilon=1e-2);
         }
     }
 
 
+    #[test]
+    fn test_normalizations() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("test_unify_width.bedgraph"),
+        ).unwrap();
+        // total signal is 0*10 + 20*20 + 40*5 + 60*7 = 1020 over 42 bp, score sum is 120
+        let rpkm = bgd.get_rpkm(NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(rpkm[1], 20.0 / 120.0 * 1e6 / 0.02, epsilon=1e-6);
+        let rpgc = bgd.get_rpgc(42, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(rpgc[3], 60.0 * 42.0 / 1020.0, epsilon=1e-12);
+        let bpm = bgd.get_bpm(NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(bpm.iter().sum::<f64>(), 1e6, epsilon=1e-6);
+        // per-kb scores are 0, 1000, 8000, and 60 / 0.007
+        assert_abs_diff_eq!(bpm[1] / bpm[2], 0.125, epsilon=1e-12);
+
+        let mut bgd = bgd;
+        bgd.to_scaled(0.5).unwrap();
+        assert_eq!(bgd.fetch_scores().unwrap(), vec![0.0, 10.0, 20.0, 30.0]);
+
+        let mut regions = BEDData::from_file(
+            &path::Path::new(TESTDIR).join("matrix_regions.bed"),
+        ).unwrap();
+        bgd.to_tpm(&mut regions, NanPolicy::Propagate).unwrap();
+        // region areas are 100 and 250 over 10 bp each
+        assert_abs_diff_eq!(regions[0].score().unwrap(), 1e6 * 100.0 / 350.0, epsilon=1e-6);
+
+        // skipped missing scores stay missing and are left out of the totals
+        let bgd = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 10, 10.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 20, f64::NAN),
+            BEDGraphRecord::new("chrA".to_string(), 20, 30, 30.0),
+        ]);
+        let normalizations = [
+            bgd.get_cpm(NanPolicy::Skip).unwrap(),
+            bgd.get_bpm(NanPolicy::Skip).unwrap(),
+        ];
+        for normalized in normalizations {
+            assert_abs_diff_eq!(normalized[0], 250_000.0, epsilon=1e-6);
+            assert!(normalized[1].is_nan());
+            assert_abs_diff_eq!(normalized[2], 750_000.0, epsilon=1e-6);
+        }
+        let rpkm = bgd.get_rpkm(NanPolicy::Skip).unwrap();
+        assert_abs_diff_eq!(rpkm[2], 30.0 / 40.0 * 1e6 / 0.01, epsilon=1e-6);
+        let rpgc = bgd.get_rpgc(30, NanPolicy::Skip).unwrap();
+        assert_abs_diff_eq!(rpgc[0], 10.0 * 30.0 / 400.0, epsilon=1e-12);
+        assert!(bgd.get_cpm(NanPolicy::Propagate).unwrap().iter().all(|x| x.is_nan()));
+        assert!(bgd.get_rpgc(30, NanPolicy::Propagate).unwrap().iter().all(|x| x.is_nan()));
+
+        let regions = BEDData::from_record_vec(vec![
+            BEDRecord::new("chrA".to_string(), 0, 10),
+            BEDRecord::new("chrA".to_string(), 10, 20),
+        ]);
+        let tpm = bgd.get_tpm(&regions, NanPolicy::Skip).unwrap();
+        assert_abs_diff_eq!(tpm[0], 1e6, epsilon=1e-6);
+        assert!(bgd.get_tpm(&regions, NanPolicy::Propagate).unwrap().iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn test_quantile_normalization() {
+        let mut bgd1 = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 5, 5.0),
+            BEDGraphRecord::new("chrA".to_string(), 5, 10, 2.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 15, 3.0),
+        ]);
+        let mut bgd2 = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 5, 4.0),
+            BEDGraphRecord::new("chrA".to_string(), 5, 10, 1.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 15, 4.0),
+        ]);
+        to_quantile_normalized(&mut [&mut bgd1, &mut bgd2], NanPolicy::Propagate).unwrap();
+        // sorted rank means are 1.5, 3.5, 4.5. The tie in bgd2 takes (3.5 + 4.5) / 2
+        assert_eq!(bgd1.fetch_scores().unwrap(), vec![4.5, 1.5, 3.5]);
+        assert_eq!(bgd2.fetch_scores().unwrap(), vec![4.0, 1.5, 4.0]);
+    }
+
     #[test]
     fn test_get_contigs() {
         let bgd = BEDGraphData::from_file(
@@ -200,6 +282,7 @@ mod tests {
             winsize,
             true,
             RollFn::Median,
+            NanPolicy::Propagate,
         ).unwrap();
         let res_scores = result.fetch_scores().unwrap();
         for (i,res) in res_scores.iter().enumerate() {
@@ -210,6 +293,7 @@ mod tests {
             winsize,
             false,
             RollFn::Median,
+            NanPolicy::Propagate,
         ).unwrap();
         let res_scores = result.fetch_scores().unwrap();
         let answer = vec![
@@ -252,6 +336,7 @@ mod tests {
             winsize,
             true,
             RollFn::Mean,
+            NanPolicy::Propagate,
         ).unwrap();
         let res_scores = result.fetch_scores().unwrap();
         for (i,res) in res_scores.iter().enumerate() {
@@ -262,6 +347,7 @@ mod tests {
             winsize,
             false,
             RollFn::Mean,
+            NanPolicy::Propagate,
         ).unwrap();
         let res_scores = result.fetch_scores().unwrap();
         let answer = vec![
@@ -280,6 +366,244 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_roll_fns() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("med_test.bedgraph"),
+        ).unwrap();
+        let winsize: usize = 3;
+        // windows for the third contig are -1.1,-1.1,-0.3 / -1.1,-0.3,-0.1 / -0.3,-0.1,-0.1 / -0.1,-0.1,-0.1
+        let result = bgd.roll_fn(winsize, false, RollFn::Sum, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(result[8].score, -2.5, epsilon=1e-12);
+        let result = bgd.roll_fn(winsize, false, RollFn::Min, NanPolicy::Propagate).unwrap();
+        assert_eq!(result.fetch_scores().unwrap()[8..], [-1.1, -1.1, -0.3, -0.1]);
+        let result = bgd.roll_fn(winsize, false, RollFn::Max, NanPolicy::Propagate).unwrap();
+        assert_eq!(result.fetch_scores().unwrap()[8..], [-0.3, -0.1, -0.1, -0.1]);
+        let result = bgd.roll_fn(winsize, false, RollFn::Quantile(0.25), NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(result[9].score, -0.7, epsilon=1e-12);
+        let result = bgd.roll_fn(winsize, false, RollFn::Sd, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(result[11].score, 0.0, epsilon=1e-12);
+        let result = bgd.roll_fn(winsize, false, RollFn::Variance, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(result[10].score, 0.04 / 3.0, epsilon=1e-12);
+        let range = RollFn::Custom(Box::new(|w: &[f64]| {
+            w.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+                - w.iter().copied().fold(f64::INFINITY, f64::min)
+        }));
+        let result = bgd.roll_fn(winsize, false, range, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(result[9].score, 1.0, epsilon=1e-12);
+
+        assert!(bgd.roll_fn(winsize, false, RollFn::Quantile(1.5), NanPolicy::Propagate).is_err());
+    }
+
+    /// naive kth smallest value plus frac toward the next, by sorting each window
+    fn naive_order_stat(values: &[f64], window_size: usize, k: usize, frac: f64) -> Vec<f64> {
+        values.windows(window_size).map(|window| {
+            let mut win = window.to_vec();
+            win.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            if frac > 0.0 {
+                win[k] + frac * (win[k + 1] - win[k])
+            } else {
+                win[k]
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn test_rolling_order_stats() {
+        // deterministic pseudo-random values with many ties
+        let mut state: u64 = 42;
+        let values: Vec<f64> = (0..2000).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((state >> 33) % 50) as f64 / 10.0
+        }).collect();
+        for window_size in [1, 2, 7, 100] {
+            for k in [0, window_size / 2, window_size - 1] {
+                let fast = rolling_order_stat(&values, window_size, k, 0.0);
+                assert_eq!(fast, naive_order_stat(&values, window_size, k, 0.0));
+            }
+            if window_size > 1 {
+                let fast = rolling_order_stat(&values, window_size, 0, 0.3);
+                let naive = naive_order_stat(&values, window_size, 0, 0.3);
+                for (a, b) in fast.iter().zip(naive.iter()) {
+                    assert_abs_diff_eq!(*a, *b, epsilon=1e-12);
+                }
+            }
+            let maxes: Vec<f64> = values.windows(window_size)
+                .map(|w| w.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+                .collect();
+            assert_eq!(rolling_extreme(&values, window_size, true), maxes);
+            let mins: Vec<f64> = values.windows(window_size)
+                .map(|w| w.iter().copied().fold(f64::INFINITY, f64::min))
+                .collect();
+            assert_eq!(rolling_extreme(&values, window_size, false), mins);
+        }
+
+        // pending deletions of distinct values are dropped once consumed, so the
+        // map stays bounded by the window size rather than the number of values
+        let mut stat = RollingOrderStat::new(0);
+        for i in 0..1000 {
+            stat.insert(i as f64);
+            if i >= 5 {
+                stat.remove((i - 5) as f64);
+            }
+            assert!(stat.delayed.len() <= 5);
+        }
+    }
+
+    #[test]
+    fn test_roll_aligned() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("small.bedgraph"),
+        ).unwrap();
+        let scores = bgd.fetch_scores().unwrap();
+        let result = bgd.roll_fn(2, false, RollFn::Mean, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(result[3].score, (scores[3] + scores[4]) / 2.0, epsilon=1e-12);
+        assert_abs_diff_eq!(result[5].score, scores[5], epsilon=1e-12);
+        let result = bgd.roll_fn(2, false, RollFn::Median, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(result[4].score, (scores[4] + scores[5]) / 2.0, epsilon=1e-12);
+        assert!(bgd.roll_fn(0, false, RollFn::Mean, NanPolicy::Propagate).is_err());
+
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("med_test.bedgraph"),
+        ).unwrap();
+        let result = bgd.roll_fn_aligned(3, WindowAlign::Right, false, RollFn::Sum, NanPolicy::Propagate).unwrap();
+        let answer = [-3.3, -2.5, -1.5, -0.5];
+        for (i,res) in result.fetch_scores().unwrap()[8..].iter().enumerate() {
+            assert_abs_diff_eq!(*res, answer[i], epsilon=1e-12);
+        }
+        let result = bgd.roll_fn_aligned(3, WindowAlign::Left, false, RollFn::Max, NanPolicy::Propagate).unwrap();
+        assert_eq!(result.fetch_scores().unwrap()[8..], [-0.1, -0.1, -0.1, -0.1]);
+    }
+
+    #[test]
+    fn test_roll_bp() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("med_test.bedgraph"),
+        ).unwrap();
+        let by_records = bgd.roll_fn(3, true, RollFn::Median, NanPolicy::Propagate).unwrap();
+        let by_bp = bgd.roll_fn_bp(15, WindowAlign::Center, true, 0.0, RollFn::Median, NanPolicy::Propagate).unwrap();
+        // the second contig's records do not start at zero, so only the others wrap identically
+        let by_bp = by_bp.fetch_scores().unwrap();
+        let by_records = by_records.fetch_scores().unwrap();
+        assert_eq!(by_bp[..4], by_records[..4]);
+        assert_eq!(by_bp[8..], by_records[8..]);
+
+        let truncated = bgd.roll_fn_bp(15, WindowAlign::Center, false, 0.0, RollFn::Median, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(truncated[8].score, -0.7, epsilon=1e-12);
+        assert_abs_diff_eq!(truncated[9].score, -0.3, epsilon=1e-12);
+        let truncated = bgd.roll_fn_bp(15, WindowAlign::Center, false, 1.0, RollFn::Median, NanPolicy::Propagate).unwrap();
+        assert!(truncated[8].score.is_nan());
+        assert_abs_diff_eq!(truncated[9].score, -0.3, epsilon=1e-12);
+
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("gaps.bedgraph"),
+        ).unwrap();
+        let result = bgd.roll_fn_bp(10, WindowAlign::Left, false, 0.5, RollFn::Mean, NanPolicy::Propagate).unwrap();
+        assert_eq!(result[0].score, 10.0);
+        let result = bgd.roll_fn_bp(10, WindowAlign::Left, false, 0.6, RollFn::Mean, NanPolicy::Propagate).unwrap();
+        assert!(result[0].score.is_nan());
+        assert_eq!(result[1].score, 4.0);
+
+        // the running computations match each window evaluated from scratch, on records
+        // of varying widths with gaps, ties and missing scores
+        let mut rng = SplitMix64::new(7);
+        let mut records: Vec<BEDGraphRecord> = Vec::new();
+        for contig in ["chrA", "chrB"] {
+            let mut start = 0;
+            for _ in 0..200 {
+                start += rng.below(4) as usize;
+                let end = start + 1 + rng.below(12) as usize;
+                let score = match rng.below(10) {
+                    0 => f64::NAN,
+                    x => (x % 4) as f64 + rng.below(3) as f64 / 2.0,
+                };
+                records.push(BEDGraphRecord::new(contig.to_string(), start, end, score));
+                start = end;
+            }
+        }
+        let bgd = BEDGraphData::from_record_vec(records);
+        let funcs = || vec![
+            RollFn::Median, RollFn::Mean, RollFn::Sum, RollFn::Min, RollFn::Max,
+            RollFn::Quantile(0.3), RollFn::Variance, RollFn::Sd,
+        ];
+        for f in 0..funcs().len() {
+            let naive = || RollFn::Custom(Box::new(move |w: &[f64]| funcs()[f].apply(w, NanPolicy::Skip).unwrap()));
+            for align in [WindowAlign::Center, WindowAlign::Left, WindowAlign::Right] {
+                for circular in [false, true] {
+                    for nan_policy in [NanPolicy::Skip, NanPolicy::Propagate] {
+                        let fast = bgd.roll_fn_bp(37, align, circular, 0.2, funcs().swap_remove(f), nan_policy).unwrap();
+                        let slow = bgd.roll_fn_bp(37, align, circular, 0.2, naive(), nan_policy).unwrap();
+                        for (a, b) in fast.iter().zip(slow.iter()) {
+                            assert!(a.score.is_nan() == b.score.is_nan());
+                            if !a.score.is_nan() {
+                                assert_abs_diff_eq!(a.score, b.score, epsilon=1e-9);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_smooth() {
+        let coefs = savgol_coefficients(5, 2, 0).unwrap();
+        let answer = [-3.0, 12.0, 17.0, 12.0, -3.0];
+        for (i,res) in coefs.iter().enumerate() {
+            assert_abs_diff_eq!(*res, answer[i] / 35.0, epsilon=1e-12);
+        }
+        let coefs = savgol_coefficients(5, 2, 1).unwrap();
+        let answer = [-2.0, -1.0, 0.0, 1.0, 2.0];
+        for (i,res) in coefs.iter().enumerate() {
+            assert_abs_diff_eq!(*res, answer[i] / 10.0, epsilon=1e-12);
+        }
+        assert!(savgol_coefficients(4, 2, 0).is_err());
+
+        // a quadratic is reproduced exactly away from contig ends
+        let records: Vec<BEDGraphRecord> = (0..10)
+            .map(|i| BEDGraphRecord::new("chrA".to_string(), i * 5, (i + 1) * 5, (i * i) as f64))
+            .collect();
+        let bgd = BEDGraphData::from_record_vec(records);
+        let sg = Smoother::SavitzkyGolay { window_size: 5, order: 2, deriv: 0 };
+        let smoothed = bgd.smooth(&sg, false, NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(smoothed[5].score, 25.0, epsilon=1e-9);
+        let sg = Smoother::SavitzkyGolay { window_size: 5, order: 2, deriv: 1 };
+        let slope = bgd.smooth(&sg, false, NanPolicy::Propagate).unwrap();
+        // d(i^2)/di = 2i per record, or 2i / 5 per bp
+        assert_abs_diff_eq!(slope[5].score, 2.0, epsilon=1e-9);
+
+        let tri = Smoother::Triangular { half_width_bp: 10 };
+        let smoothed = bgd.smooth(&tri, false, NanPolicy::Propagate).unwrap();
+        let answer = (9.0 + 2.0 * 16.0 + 3.0 * 25.0 + 2.0 * 36.0 + 49.0) / 9.0;
+        assert_abs_diff_eq!(smoothed[5].score, answer, epsilon=1e-9);
+
+        // sigma is one record, so the kernel spans four records each side with weights
+        // exp(-k^2 / 2). On i^2 the odd terms cancel, leaving i^2 + sum(w k^2) / sum(w).
+        let gauss = Smoother::Gaussian { sigma_bp: 5.0 };
+        let smoothed = bgd.smooth(&gauss, false, NanPolicy::Propagate).unwrap();
+        let weights = [1.0, 0.6065306597126334, 0.1353352832366127, 0.011108996538242306, 0.00033546262790251185];
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f64>();
+        let spread = 2.0 * (1..5).map(|k| weights[k] * (k * k) as f64).sum::<f64>() / total;
+        assert_abs_diff_eq!(spread, 0.999928, epsilon=1e-6);
+        assert_abs_diff_eq!(smoothed[4].score, 16.0 + spread, epsilon=1e-9);
+        assert_abs_diff_eq!(smoothed[5].score, 25.0 + spread, epsilon=1e-9);
+
+        assert!(bgd.smooth(&Smoother::Gaussian { sigma_bp: 0.0 }, false, NanPolicy::Propagate).is_err());
+        assert!(bgd.smooth(&Smoother::Gaussian { sigma_bp: -1.0 }, false, NanPolicy::Propagate).is_err());
+        assert!(bgd.smooth(&Smoother::Triangular { half_width_bp: 4 }, false, NanPolicy::Propagate).is_err());
+        assert!(bgd.smooth(&Smoother::Exponential { half_life_bp: 0.0 }, false, NanPolicy::Propagate).is_err());
+
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("small.bedgraph"),
+        ).unwrap();
+
+        let ema = Smoother::Exponential { half_life_bp: 5.0 };
+        let smoothed = bgd.smooth(&ema, false, NanPolicy::Propagate).unwrap();
+        // the weight halves every record, and the first record is its own average
+        let answer = 0.5 * bgd[4].score + 0.5 * bgd[3].score;
+        assert_abs_diff_eq!(smoothed[4].score, answer, epsilon=1e-12);
+    }
+
     #[test]
     fn test_padding() {
         let bgd = BEDGraphData::from_file(
@@ -359,21 +683,21 @@ mod tests {
         let bgd = BEDGraphData::from_file(
             &path::Path::new(TESTDIR).join("small.bedgraph"),
         ).unwrap();
-        let med = bgd.median().unwrap();
+        let med = bgd.median(NanPolicy::Propagate).unwrap();
         let answer = -0.386565212080191;
         assert_eq!(med, answer);
 
         let bgd = BEDGraphData::from_file(
             &path::Path::new(TESTDIR).join("small2.bedgraph"),
         ).unwrap();
-        let med = bgd.median().unwrap();
+        let med = bgd.median(NanPolicy::Propagate).unwrap();
         let answer = (-0.386565212080191 + -0.1719770035449314) / 2.0;
         assert_eq!(med, answer);
 
         let bgd = BEDGraphData::from_file(
             &path::Path::new(TESTDIR).join("test.bedgraph"),
         ).unwrap();
-        let med = bgd.median().unwrap();
+        let med = bgd.median(NanPolicy::Propagate).unwrap();
         let answer = 0.0623446015925157;
         assert_eq!(med, answer);
     }
@@ -383,7 +707,7 @@ mod tests {
         let bgd = BEDGraphData::from_file(
             &path::Path::new(TESTDIR).join("test.bedgraph"),
         ).unwrap();
-        let mean = bgd.mean().unwrap();
+        let mean = bgd.mean(NanPolicy::Propagate).unwrap();
         let answer = 0.4037938099229696;
         assert_eq!(mean, answer);
     }
@@ -393,646 +717,7566 @@ mod tests {
         let bgd = BEDGraphData::from_file(
             &path::Path::new(TESTDIR).join("small.bedgraph"),
         ).unwrap();
-        let mad = bgd.mad().unwrap();
-        let answer = 0.4451182037397217;
-        assert_eq!(mad, answer);
+        let mad = bgd.mad(NanPolicy::Propagate).unwrap();
+        let answer = 0.44661979510781635;
+        assert_abs_diff_eq!(mad, answer, epsilon=1e-12);
+    }
+
+    fn bed_coords(bd: &BEDData) -> Vec<(String, usize, usize)> {
+        bd.iter()
+            .map(|x| (x.seqname.to_string(), x.start, x.end))
+            .collect()
     }
 
     #[test]
-    fn test_robust_z() {
-        let bgd = BEDGraphData::from_file(
-            &path::Path::new(TESTDIR).join("small.bedgraph"),
+    fn test_read_bed() {
+        let bd = BEDData::from_file(
+            &path::Path::new(TESTDIR).join("small.bed"),
         ).unwrap();
-        let rz = bgd.robust_z().unwrap();
-        let answer: Vec<f64> = vec![
-            0.686841,
-            0.686841,
-            0.686841,
-            -0.357335,
-            -0.676776,
-            -0.145853,
-            -1.22621,
-            -1.51412e-15,
-            0.325172,
-        ];
-        let zscores = rz.fetch_scores().unwrap();
-        for (i,res) in zscores.iter().enumerate() {
-            assert_abs_diff_eq!(*res, answer[i], epsilon=1e-5);
-        }
+        assert_eq!(bd.len(), 4);
+        let answer = BEDRecord::new_stranded(
+            "chr1".to_string(),
+            90,
+            95,
+            Some("peak2".to_string()),
+            None,
+            Strand::Reverse,
+        );
+        assert_eq!(bd[1], answer);
+        assert_eq!(bd[2].score(), Some(2.5));
+
+        let genome = Genome::from_file(
+            &path::Path::new(TESTDIR).join("small.genome"),
+        ).unwrap();
+        assert_eq!(genome.get_length("chr1").unwrap(), 100);
+        assert!(!genome.is_circular("chr1").unwrap());
+        assert!(genome.is_circular("plasmid").unwrap());
+        assert!(genome.get_length("chr2").is_err());
     }
-}
 
-#[derive(Debug, Clone)]
-struct BEDGraphRecordNotDivisible {
-    start: usize,
-    end: usize,
-    width: usize,
-}
+    #[test]
+    fn test_slop() {
+        let bd = BEDData::from_file(
+            &path::Path::new(TESTDIR).join("small.bed"),
+        ).unwrap();
+        let genome = Genome::from_file(
+            &path::Path::new(TESTDIR).join("small.genome"),
+        ).unwrap();
+        let slopped = bd.slop(&genome, 15, 5, true).unwrap();
+        let answer = vec![
+            ("chr1".to_string(), 0, 25),
+            ("chr1".to_string(), 85, 100),
+            ("plasmid".to_string(), 40, 50),
+            ("plasmid".to_string(), 0, 15),
+            ("plasmid".to_string(), 35, 50),
+            ("plasmid".to_string(), 0, 13),
+        ];
+        assert_eq!(bed_coords(&slopped), answer);
+        assert_eq!(slopped[3].name(), Some("peak3"));
+    }
 
-// Implement the Display trait for BEDGraphRecordNotDivisible
-impl fmt::Display for BEDGraphRecordNotDivisible {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "BEDGraphRecord width is not evenly divisible: start = {}, end = {}, desired width = {}. Exiting without printing results.",
-            self.start, self.end, self.width
-        )
+    #[test]
+    fn test_flank() {
+        let bd = BEDData::from_file(
+            &path::Path::new(TESTDIR).join("small.bed"),
+        ).unwrap();
+        let genome = Genome::from_file(
+            &path::Path::new(TESTDIR).join("small.genome"),
+        ).unwrap();
+        let promoters = bd.flank(&genome, 10, 0, true).unwrap();
+        let answer = vec![
+            ("chr1".to_string(), 0, 10),
+            ("chr1".to_string(), 95, 100),
+            ("plasmid".to_string(), 45, 50),
+            ("plasmid".to_string(), 0, 5),
+            ("plasmid".to_string(), 48, 50),
+            ("plasmid".to_string(), 0, 8),
+        ];
+        assert_eq!(bed_coords(&promoters), answer);
     }
-}
 
-// Implement the Error trait for BEDGraphRecordNotDivisible. Since we don't have any additional
-// data to provide, we can just defer to the Display implementation.
-impl Error for BEDGraphRecordNotDivisible {}
+    #[test]
+    fn test_resize() {
+        let bd = BEDData::from_file(
+            &path::Path::new(TESTDIR).join("small.bed"),
+        ).unwrap();
+        let genome = Genome::from_file(
+            &path::Path::new(TESTDIR).join("small.genome"),
+        ).unwrap();
+        let resized = bd.resize(&genome, 4, Anchor::FivePrime).unwrap();
+        let answer = vec![
+            ("chr1".to_string(), 10, 14),
+            ("chr1".to_string(), 91, 95),
+            ("plasmid".to_string(), 5, 9),
+            ("plasmid".to_string(), 44, 48),
+        ];
+        assert_eq!(bed_coords(&resized), answer);
 
-pub enum RollFn {
-    Median,
-    Mean,
-}
+        let resized = bd.resize(&genome, 4, Anchor::Center).unwrap();
+        let answer = vec![
+            ("chr1".to_string(), 13, 17),
+            ("chr1".to_string(), 90, 94),
+            ("plasmid".to_string(), 5, 9),
+            ("plasmid".to_string(), 42, 46),
+        ];
+        assert_eq!(bed_coords(&resized), answer);
 
-fn median(vec: &mut Vec<f64>) -> Result<f64, Box<dyn Error>> {
-    vec.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    let length = vec.len();
-    let mid = length / 2;
-    if length % 2 == 1 {
-        Ok(vec[mid])
-    } else {
-        let median = (vec[mid-1] + vec[mid]) / 2.0;
-        Ok(median)
+        // zero-width records are rejected on linear and circular contigs alike
+        assert!(bd.resize(&genome, 0, Anchor::Center).is_err());
+        let circular = BEDData::from_record_vec(vec![BEDRecord::new("plasmid".to_string(), 5, 9)]);
+        assert!(circular.resize(&genome, 0, Anchor::FivePrime).is_err());
     }
-}
 
-fn mean(vec: &Vec<f64>) -> Result<f64, Box<dyn Error>> {
-    let sum: f64 = vec.iter().sum();
-    let count = vec.len() as f64;
-    Ok(sum / count)
-}
+    #[test]
+    fn test_region_summary() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("test_unify_width.bedgraph"),
+        ).unwrap();
+        let regions = BEDData::from_file(
+            &path::Path::new(TESTDIR).join("summary_regions.bed"),
+        ).unwrap();
+        let summaries = bgd.region_summary(&regions, NanPolicy::Propagate).unwrap();
 
-fn mad(vec: &mut Vec<f64>) -> Result<f64, Box<dyn Error>> {
-    let mean_score = mean(vec)?;
-    let mut abs_devs: Vec<f64> = vec.iter()
-        .map(|a| (a-mean_score).abs())
-        .collect();
-    abs_devs.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    let mad = median(&mut abs_devs)?;
-    Ok(mad)
-}
+        assert_eq!(summaries[0].covered(), 27);
+        assert_abs_diff_eq!(summaries[0].sum(), 480.0, epsilon=1e-12);
+        assert_abs_diff_eq!(summaries[0].mean_covered(), 480.0 / 27.0, epsilon=1e-12);
+        assert_abs_diff_eq!(summaries[0].min(), 0.0, epsilon=1e-12);
+        assert_abs_diff_eq!(summaries[0].max(), 40.0, epsilon=1e-12);
+        assert_abs_diff_eq!(summaries[0].median(), 20.0, epsilon=1e-12);
 
-fn robust_z(x: f64, median: f64, mad: f64) -> f64 {
-    0.6745 * (x - median) / mad
-}
+        assert_eq!(summaries[1].covered(), 4);
+        assert_abs_diff_eq!(summaries[1].mean_covered(), 60.0, epsilon=1e-12);
+        assert_abs_diff_eq!(summaries[1].mean_region(), 20.0, epsilon=1e-12);
+        assert_abs_diff_eq!(summaries[1].fraction_covered(), 4.0 / 12.0, epsilon=1e-12);
 
-/// struct to define a single line of a bed file
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct BEDRecord {
-    seqname: String,
-    start: usize,
-    end: usize,
-}
+        assert_eq!(summaries[2].covered(), 0);
+        assert_abs_diff_eq!(summaries[2].mean_region(), 0.0, epsilon=1e-12);
+        assert!(summaries[2].mean_covered().is_nan());
 
-impl BEDRecord {
-    fn new(
-        seqname: String,
-        start: usize,
-        end: usize,
-    ) -> BEDRecord {
-        BEDRecord {
-            seqname,
-            start,
-            end,
-        }
+        let empty = BEDRecord::new("chr".to_string(), 10, 10);
+        let summary = RegionSummary::from_pairs(&empty, &mut []);
+        assert!(summary.fraction_covered().is_nan());
+        assert!(summary.median().is_nan());
     }
 
-    fn set(&mut self, seqname: &str, start: &usize, end: &usize) {
-        self.set_seqname(seqname);
-        self.set_start(start);
-        self.set_end(end);
+    #[test]
+    fn test_weighted_median() {
+        assert_eq!(weighted_median(&mut [(3.0, 1), (1.0, 2), (2.0, 4)]), 2.0);
+        assert_eq!(weighted_median(&mut [(1.0, 2), (2.0, 0), (4.0, 2)]), 2.5);
+        assert!(weighted_median(&mut [(1.0, 0), (2.0, 0)]).is_nan());
+        assert!(weighted_median(&mut []).is_nan());
     }
 
-    fn set_seqname(&mut self, seqname: &str) {
-        self.seqname = String::from(seqname);
+    #[test]
+    fn test_compute_matrix() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("test_unify_width.bedgraph"),
+        ).unwrap();
+        let regions = BEDData::from_file(
+            &path::Path::new(TESTDIR).join("matrix_regions.bed"),
+        ).unwrap();
+        let mode = MatrixMode::ReferencePoint {
+            anchor: Anchor::FivePrime,
+            upstream: 10,
+            downstream: 10,
+        };
+        let mat = bgd.compute_matrix(&regions, &mode, 5).unwrap();
+        assert_eq!(mat.ncol(), 4);
+        assert_eq!(mat.row_labels(), ["g1", "g2"]);
+        assert_eq!(mat.row(0), [0.0, 0.0, 20.0, 20.0]);
+        assert!(mat.get(1, 0).is_nan());
+        assert_eq!(mat.row(1)[1..], [60.0, 60.0, 40.0]);
+        let means = mat.column_summary(ColumnStat::Mean).unwrap();
+        assert_eq!(means, vec![0.0, 30.0, 40.0, 30.0]);
+        let sems = mat.column_summary(ColumnStat::Sem).unwrap();
+        assert!(sems[0].is_nan());
+        assert_abs_diff_eq!(sems[1], 30.0, epsilon=1e-12);
+
+        let mode = MatrixMode::ScaleRegions {
+            upstream: 5,
+            body_length: 10,
+            downstream: 5,
+        };
+        let mat = bgd.compute_matrix(&regions, &mode, 5).unwrap();
+        assert_eq!(mat.row(0), [0.0, 20.0, 20.0, 20.0]);
+        assert_eq!(mat.row(1), [60.0, 60.0, 40.0, 20.0]);
+
+        assert!(bgd.compute_matrix(&regions, &mode, 3).is_err());
     }
 
-    fn set_start(&mut self, start: &usize) {
-        self.start = *start;
+    #[test]
+    fn test_rebin() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("test_unify_width.bedgraph"),
+        ).unwrap();
+        let rebinned = bgd.rebin(20, Aggregation::Mean, f64::NAN, NanPolicy::Propagate).unwrap();
+        assert_eq!(rebinned.len(), 3);
+        assert_eq!(rebinned[2].start, 40);
+        assert_eq!(rebinned[2].end, 42);
+        assert_eq!(rebinned.fetch_scores().unwrap(), vec![10.0, 35.0, 60.0]);
+
+        let rebinned = bgd.rebin(20, Aggregation::Max, f64::NAN, NanPolicy::Propagate).unwrap();
+        assert_eq!(rebinned.fetch_scores().unwrap(), vec![20.0, 60.0, 60.0]);
+        let rebinned = bgd.rebin(20, Aggregation::Sum, f64::NAN, NanPolicy::Propagate).unwrap();
+        assert_eq!(rebinned.fetch_scores().unwrap(), vec![200.0, 700.0, 120.0]);
+        let rebinned = bgd.rebin(20, Aggregation::Median, f64::NAN, NanPolicy::Propagate).unwrap();
+        assert_eq!(rebinned.fetch_scores().unwrap(), vec![10.0, 30.0, 60.0]);
+
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("gaps.bedgraph"),
+        ).unwrap();
+        let rebinned = bgd.rebin(10, Aggregation::Mean, 0.0, NanPolicy::Propagate).unwrap();
+        assert_eq!(rebinned.fetch_scores().unwrap(), vec![5.0, 4.0]);
+        let rebinned = bgd.rebin(10, Aggregation::Mean, f64::NAN, NanPolicy::Propagate).unwrap();
+        assert_eq!(rebinned.fetch_scores().unwrap(), vec![10.0, 4.0]);
     }
 
-    fn set_end(&mut self, end: &usize) {
-        self.end = *end;
+    #[test]
+    fn test_fill_gaps() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("gaps.bedgraph"),
+        ).unwrap();
+        let genome = Genome::from_contig_vec(vec![
+            Contig::new("chrA".to_string(), 30, false),
+            Contig::new("chrB".to_string(), 10, false),
+        ]);
+        let filled = bgd.fill_gaps(&genome, 0.0).unwrap();
+        let answer = vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 5, 10.0),
+            BEDGraphRecord::new("chrA".to_string(), 5, 10, 0.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 20, 4.0),
+            BEDGraphRecord::new("chrA".to_string(), 20, 30, 0.0),
+            BEDGraphRecord::new("chrB".to_string(), 0, 10, 0.0),
+        ];
+        assert_eq!(filled.data, answer);
+
+        let dense = bgd.densify(&genome, 5, 0.0).unwrap();
+        assert_eq!(dense.len(), 8);
+        assert_eq!(dense.fetch_scores().unwrap(), vec![10.0, 0.0, 4.0, 4.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let sparse = dense.sparsify(0.0);
+        let answer = vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 5, 10.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 20, 4.0),
+        ];
+        assert_eq!(sparse.data, answer);
+        assert_eq!(dense.compress().len(), 5);
+
+        let genome = Genome::from_contig_vec(vec![
+            Contig::new("chrB".to_string(), 10, false),
+        ]);
+        assert!(bgd.fill_gaps(&genome, 0.0).is_err());
     }
-}
 
-/// struct to define a single line of a bedgraph file
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct BEDGraphRecord {
-    seqname: String,
-    start: usize,
-    end: usize,
-    score: f64,
-}
+    #[test]
+    fn test_union_bedgraph() {
+        let bgd1 = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("gaps.bedgraph"),
+        ).unwrap();
+        let bgd2 = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 3, 12, 1.0),
+            BEDGraphRecord::new("chrA".to_string(), 25, 30, 2.0),
+            BEDGraphRecord::new("chrB".to_string(), 0, 5, 3.0),
+        ]);
+        let union = union_bedgraph(&[&bgd1, &bgd2], &["a", "b"], 0.0).unwrap();
+        let answer = vec![
+            UnionBEDGraphRecord::new("chrA".to_string(), 0, 3, vec![10.0, 0.0]),
+            UnionBEDGraphRecord::new("chrA".to_string(), 3, 5, vec![10.0, 1.0]),
+            UnionBEDGraphRecord::new("chrA".to_string(), 5, 10, vec![0.0, 1.0]),
+            UnionBEDGraphRecord::new("chrA".to_string(), 10, 12, vec![4.0, 1.0]),
+            UnionBEDGraphRecord::new("chrA".to_string(), 12, 20, vec![4.0, 0.0]),
+            UnionBEDGraphRecord::new("chrA".to_string(), 25, 30, vec![0.0, 2.0]),
+            UnionBEDGraphRecord::new("chrB".to_string(), 0, 5, vec![0.0, 3.0]),
+        ];
+        assert_eq!(union.data, answer);
+        assert_eq!(union.get_track(1).unwrap()[3].score, 1.0);
+        assert!(union_bedgraph(&[&bgd1, &bgd2], &["a"], 0.0).is_err());
 
-impl BEDGraphRecord {
-    pub fn new(
-        seqname: String,
-        start: usize,
-        end: usize,
-        score: f64,
-    ) -> BEDGraphRecord {
-        BEDGraphRecord {
-            seqname,
-            start,
-            end,
-            score,
-        }
+        let fname = unique_temp_path("test_union.bedgraph");
+        union.to_file(&fname, true).unwrap();
+        let read_union = UnionBEDGraphData::from_file(&fname, true).unwrap();
+        assert_eq!(read_union.sample_names(), ["a", "b"]);
+        assert_eq!(read_union.data, answer);
+        std::fs::remove_file(&fname).unwrap();
     }
 
-    fn set_score(&mut self, new_score: f64) {
-        self.score = new_score;
+    #[test]
+    fn test_compare() {
+        let bgd1 = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("gaps.bedgraph"),
+        ).unwrap();
+        let bgd2 = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 3, 12, 2.0),
+            BEDGraphRecord::new("chrA".to_string(), 12, 20, 4.0),
+        ]);
+        let ratio = bgd1.compare(&bgd2, TrackOp::Ratio, NonFinitePolicy::Error).unwrap();
+        let answer = vec![
+            BEDGraphRecord::new("chrA".to_string(), 3, 5, 5.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 12, 2.0),
+            BEDGraphRecord::new("chrA".to_string(), 12, 20, 1.0),
+        ];
+        assert_eq!(ratio.data, answer);
+
+        let diff = bgd1.compare(&bgd2, TrackOp::Subtract, NonFinitePolicy::Keep).unwrap();
+        assert_eq!(diff.fetch_scores().unwrap(), vec![8.0, 2.0, 0.0]);
+        let lfc = bgd1.compare(&bgd2, TrackOp::Log2Ratio { pseudocount: 1.0 }, NonFinitePolicy::Keep).unwrap();
+        assert_abs_diff_eq!(lfc[0].score, (11.0_f64 / 3.0).log2(), epsilon=1e-12);
+        let custom = bgd1.combine(&bgd2, |a, b| a.max(b), NonFinitePolicy::Keep).unwrap();
+        assert_eq!(custom.fetch_scores().unwrap(), vec![10.0, 4.0, 4.0]);
+
+        // one zero bin in the denominator affects only its own record
+        let denom = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 3, 12, 0.0),
+            BEDGraphRecord::new("chrA".to_string(), 12, 20, 4.0),
+        ]);
+        let ratio = bgd1.compare(&denom, TrackOp::Ratio, NonFinitePolicy::Keep).unwrap();
+        assert_eq!(ratio.fetch_scores().unwrap(), vec![f64::INFINITY, f64::INFINITY, 1.0]);
+        let ratio = bgd1.compare(&denom, TrackOp::Ratio, NonFinitePolicy::Nan).unwrap();
+        let scores = ratio.fetch_scores().unwrap();
+        assert!(scores[0].is_nan() && scores[1].is_nan());
+        assert_eq!(scores[2], 1.0);
+        assert!(bgd1.compare(&denom, TrackOp::Ratio, NonFinitePolicy::Error).is_err());
+        let lfc = bgd1.compare(&denom, TrackOp::Log2Ratio { pseudocount: 0.0 }, NonFinitePolicy::Keep).unwrap();
+        assert_eq!(lfc[0].score, f64::INFINITY);
+        let zeros = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 3, 20, 0.0),
+        ]);
+        let lfc = zeros.compare(&zeros, TrackOp::Log2Ratio { pseudocount: 1.0 }, NonFinitePolicy::Error).unwrap();
+        assert_eq!(lfc.fetch_scores().unwrap(), vec![0.0]);
+        let missing = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 3, 20, f64::NAN),
+        ]);
+        let ratio = bgd1.compare(&missing, TrackOp::Ratio, NonFinitePolicy::Error).unwrap();
+        assert!(ratio.fetch_scores().unwrap().iter().all(|x| x.is_nan()));
     }
 
-    // This method attempts to split the BEDGraphRecord into several with equal width 'w'.
-    fn split_evenly(
-            &self,
-            w: usize,
+    #[test]
+    fn test_grouped_z() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("small.bedgraph"),
+        ).unwrap();
+        let genome_z = bgd.z_score(ZScore::Robust(Dispersion::Mad), &ZScope::Genome, NanPolicy::Propagate).unwrap();
+        assert_eq!(genome_z.fetch_scores().unwrap(), bgd.robust_z(NanPolicy::Propagate).unwrap().fetch_scores().unwrap());
+
+        let contig_z = bgd.z_score(ZScore::Standard, &ZScope::Contig, NanPolicy::Propagate).unwrap();
+        let scores = bgd.fetch_scores().unwrap();
+        let ctg_mean = (scores[3] + scores[4] + scores[5]) / 3.0;
+        let ctg_sd = ((scores[3..6].iter().map(|x| (x - ctg_mean).powi(2)).sum::<f64>()) / 2.0).sqrt();
+        assert_abs_diff_eq!(contig_z[3].score, (scores[3] - ctg_mean) / ctg_sd, epsilon=1e-12);
+        // the first contig has constant scores, so its SD is zero
+        assert!(contig_z[0].score.is_nan());
+
+        let regions = BEDData::from_record_vec(vec![
+            BEDRecord::new("pBRP02".to_string(), 0, 15),
+        ]);
+        let region_z = bgd.z_score(ZScore::Standard, &ZScope::Regions(&regions), NanPolicy::Propagate).unwrap();
+        assert!(region_z[3].score.is_nan());
+        assert_abs_diff_eq!(region_z[6..].iter().map(|x| x.score).sum::<f64>(), 0.0, epsilon=1e-12);
+
+        // 5 bp records, so a 15 bp window holds up to three of them
+        let window_z = bgd.z_score(ZScore::Standard, &ZScope::Window(15), NanPolicy::Propagate).unwrap();
+        let win_mean = (scores[3] + scores[4]) / 2.0;
+        let win_sd = ((scores[3] - win_mean).powi(2) * 2.0).sqrt();
+        assert_abs_diff_eq!(window_z[3].score, (scores[3] - win_mean) / win_sd, epsilon=1e-12);
+        assert_abs_diff_eq!(window_z[4].score, contig_z[4].score, epsilon=1e-12);
+        // a 10 bp window holds the records whose midpoints lie 5 bp before or on the midpoint
+        let even_z = bgd.z_score(ZScore::Standard, &ZScope::Window(10), NanPolicy::Propagate).unwrap();
+        assert_abs_diff_eq!(even_z[4].score, (scores[4] - scores[3]).signum() / 2_f64.sqrt(), epsilon=1e-12);
+        for dispersion in [Dispersion::Mad, Dispersion::Iqr, Dispersion::Sd, Dispersion::Qn] {
+            let kind = ZScore::Robust(dispersion);
+            let window_z = bgd.z_score(kind, &ZScope::Window(15), NanPolicy::Propagate).unwrap();
+            let contig_z = bgd.z_score(kind, &ZScope::Contig, NanPolicy::Propagate).unwrap();
+            assert_abs_diff_eq!(window_z[4].score, contig_z[4].score, epsilon=1e-12);
+        }
+        assert!(bgd.z_score(ZScore::Standard, &ZScope::Window(0), NanPolicy::Propagate).is_err());
+    }
+
+    #[test]
+    fn test_call_regions() {
+        let scores = [1.0, 5.0, 6.0, 2.0, 7.0, 1.0, 2.0, 1.0, 8.0, 2.0];
+        let records: Vec<BEDGraphRecord> = scores.iter().enumerate()
+            .map(|(i, x)| BEDGraphRecord::new("chrA".to_string(), i * 10, (i + 1) * 10, *x))
+            .collect();
+        let bgd = BEDGraphData::from_record_vec(records);
+
+        let regions = bgd.call_regions(Threshold::Absolute(5.0), 0, 10, 1).unwrap();
+        assert_eq!(regions.len(), 2);
+        assert_eq!((regions[0].start(), regions[0].end()), (10, 50));
+        assert_eq!(regions[0].summit(), 45);
+        assert_eq!(regions[0].n_bins(), 3);
+        assert_eq!(regions[0].max_score(), 7.0);
+        assert_eq!(regions[0].mean_score(), 6.0);
+        assert_eq!(regions[0].auc(), 180.0);
+        assert_eq!((regions[1].start(), regions[1].end()), (80, 90));
+
+        let regions = bgd.call_regions(Threshold::Absolute(5.0), 0, 0, 1).unwrap();
+        assert_eq!(regions.len(), 3);
+        let regions = bgd.call_regions(Threshold::Absolute(5.0), 0, 10, 2).unwrap();
+        assert_eq!(regions.len(), 1);
+        let regions = bgd.call_regions(Threshold::Absolute(5.0), 50, 10, 1).unwrap();
+        assert!(regions.is_empty());
+
+        // the median is 2 and the MAD is 1, so only scores of 7 and 8 pass
+        let regions = bgd.call_regions(Threshold::RobustZ(3.0), 0, 10, 1).unwrap();
+        assert_eq!(regions.len(), 2);
+        let peak = regions[0].to_narrowpeak("peak1");
+        assert_eq!(peak.start(), 40);
+        assert_eq!(peak.peak(), 5);
+        let fname = unique_temp_path("test_call.narrowPeak");
+        NarrowPeakData::from_record_vec(vec![peak.clone()]).to_file(&fname).unwrap();
+        let npd = NarrowPeakData::from_file(&fname).unwrap();
+        assert_eq!(npd[0], peak);
+        std::fs::remove_file(&fname).unwrap();
+    }
+
+    #[test]
+    fn test_poisson() {
+        assert_abs_diff_eq!(poisson_mlog10_sf(1, 1.0), 0.19920008462778144, epsilon=1e-9);
+        assert_abs_diff_eq!(poisson_mlog10_sf(10, 1.0), 6.9530154928538925, epsilon=1e-9);
+        assert_abs_diff_eq!(poisson_mlog10_sf(3, 5.5), 0.04018445579849073, epsilon=1e-9);
+        assert_abs_diff_eq!(ln_gamma(10.0), 362880.0_f64.ln(), epsilon=1e-9);
+    }
+
+    #[test]
+    fn test_p_adjust() {
+        let p = [0.01, 0.04, 0.03, 0.5, f64::NAN];
+        let bh = p_adjust(&p, PAdjust::BenjaminiHochberg).unwrap();
+        let answer = [0.04, 0.16 / 3.0, 0.16 / 3.0, 0.5];
+        for (i,res) in answer.iter().enumerate() {
+            assert_abs_diff_eq!(bh[i], *res, epsilon=1e-12);
+        }
+        assert!(bh[4].is_nan());
+        let by = p_adjust(&p, PAdjust::BenjaminiYekutieli).unwrap();
+        assert_abs_diff_eq!(by[0], 0.04 * (1.0 + 0.5 + 1.0 / 3.0 + 0.25), epsilon=1e-12);
+        assert_abs_diff_eq!(by[3], 1.0, epsilon=1e-12);
+        let bonf = p_adjust(&p, PAdjust::Bonferroni).unwrap();
+        assert_abs_diff_eq!(bonf[2], 0.12, epsilon=1e-12);
+        let holm = p_adjust(&p, PAdjust::Holm).unwrap();
+        let answer = [0.04, 0.09, 0.09, 0.5];
+        for (i,res) in answer.iter().enumerate() {
+            assert_abs_diff_eq!(holm[i], *res, epsilon=1e-12);
+        }
+        // one of four p-values exceeds lambda = 0.5, so pi0 = 1 / (4 * 0.5)
+        let storey = p_adjust(&[0.01, 0.04, 0.03, 0.6], PAdjust::Storey { lambda: 0.5 }).unwrap();
+        assert_abs_diff_eq!(storey[0], 0.02, epsilon=1e-12);
+        assert!(p_adjust(&[0.01, 0.02, 0.3], PAdjust::Storey { lambda: 0.5 }).is_err());
+        assert!(p_adjust(&[], PAdjust::Storey { lambda: 0.5 }).unwrap().is_empty());
+        assert!(p_adjust(&[1.5], PAdjust::Holm).is_err());
+
+        let tiny = p_adjust_mlog10(&[400.0, 2.0], PAdjust::Bonferroni).unwrap();
+        assert_abs_diff_eq!(tiny[0], 400.0 - 2_f64.log10(), epsilon=1e-9);
+
+        let bgd = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 5, 0.01),
+            BEDGraphRecord::new("chrA".to_string(), 5, 10, 0.04),
+        ]);
+        let adjusted = bgd.p_adjust(PAdjust::Bonferroni, false).unwrap();
+        assert_abs_diff_eq!(adjusted[1].score, 0.08, epsilon=1e-12);
+    }
+
+    #[test]
+    fn test_call_peaks() {
+        let treat: Vec<BEDGraphRecord> = (0..200)
+            .map(|i| {
+                let score = if (100..102).contains(&i) { 30.0 } else { 2.0 };
+                BEDGraphRecord::new("chrA".to_string(), i * 100, (i + 1) * 100, score)
+            }).collect();
+        let treat = BEDGraphData::from_record_vec(treat);
+        let control = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 20_000, 2.0),
+        ]);
+        let peaks = treat.call_peaks(&control, &PeakCallParams::default()).unwrap();
+        assert_eq!(peaks.len(), 1);
+        assert_eq!((peaks[0].start(), peaks[0].end()), (10_000, 10_200));
+        assert_eq!(peaks[0].name(), "peak_1");
+        assert_eq!(peaks[0].summit(), 10_050);
+        let lambda = (2.0 * 19_800.0 + 30.0 * 200.0) / 20_000.0;
+        assert_abs_diff_eq!(peaks[0].p_value(), poisson_mlog10_sf(30, lambda), epsilon=1e-9);
+        assert_abs_diff_eq!(peaks[0].signal_value(), 31.0 / (lambda + 1.0), epsilon=1e-9);
+        // BH over the 200 bins, in which the two enriched bins tie for the smallest
+        // p-value, multiplies their p-value by 200 / 2
+        assert_abs_diff_eq!(peaks[0].q_value(), peaks[0].p_value() - 100_f64.log10(), epsilon=1e-9);
+    }
+
+    #[test]
+    fn test_idr_simulated() {
+        // pairs drawn from the copula mixture itself: a reproducible bivariate normal
+        // component with known parameters and an independent standard normal one
+        let (mu, sigma, rho, mix) = (2.5, 1.0, 0.8, 0.6);
+        let n = 2_000;
+        let mut rng = SplitMix64::new(11);
+        let mut normal = || {
+            let u1 = (rng.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+            let u2 = (rng.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+            (-2.0 * (1.0 - u1).ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+        };
+        let mut rep1: Vec<NarrowPeakRecord> = Vec::new();
+        let mut rep2: Vec<NarrowPeakRecord> = Vec::new();
+        let mut z: Vec<(f64, f64)> = Vec::new();
+        for i in 0..n {
+            let (a, b) = (normal(), normal());
+            let (z1, z2) = if i < (mix * n as f64) as usize {
+                (mu + sigma * a, mu + sigma * (rho * a + (1.0 - rho * rho).sqrt() * b))
+            } else {
+                (a, b)
+            };
+            z.push((z1, z2));
+            for (rep, signal) in [(&mut rep1, z1), (&mut rep2, z2)] {
+                rep.push(NarrowPeakRecord::new(
+                    "chrA".to_string(), i * 100, i * 100 + 50, format!("p{}", i),
+                    0.0, Strand::Unknown, signal, -1.0, -1.0, 25,
+                ));
+            }
+        }
+        let rep1 = NarrowPeakData::from_record_vec(rep1);
+        let rep2 = NarrowPeakData::from_record_vec(rep2);
+        let result = rep1.idr(&rep2, &IdrParams::default()).unwrap();
+        assert_eq!(result.len(), n);
+
+        let model = result.model();
+        assert_abs_diff_eq!(model.mu(), mu, epsilon=0.15);
+        assert_abs_diff_eq!(model.sigma(), sigma, epsilon=0.25);
+        assert_abs_diff_eq!(model.rho(), rho, epsilon=0.05);
+        assert_abs_diff_eq!(model.mix(), mix, epsilon=0.05);
+
+        // classifying pairs by local IDR < 0.5 does nearly as well as using the
+        // posterior of the true model
+        let truth = IdrModel { mu, sigma, rho, mix, log_likelihood: 0.0, n_iter: 0 };
+        let mut fitted_errors = 0;
+        let mut bayes_errors = 0;
+        for x in result.iter() {
+            let i = x.rep1().start() / 100;
+            let reproducible = i < (mix * n as f64) as usize;
+            let (f1, f0) = truth.densities(z[i].0, z[i].1);
+            if (mix * f1 > (1.0 - mix) * f0) != reproducible {
+                bayes_errors += 1;
+            }
+            if (x.local_idr() < 0.5) != reproducible {
+                fitted_errors += 1;
+            }
+        }
+        assert!(fitted_errors <= bayes_errors + n / 50);
+    }
+
+    #[test]
+    fn test_idr() {
+        let rep1 = NarrowPeakData::from_file(
+            &path::Path::new(TESTDIR).join("idr_rep1.narrowPeak"),
+        ).unwrap();
+        let rep2 = NarrowPeakData::from_file(
+            &path::Path::new(TESTDIR).join("idr_rep2.narrowPeak"),
+        ).unwrap();
+        let result = rep1.idr(&rep2, &IdrParams::default()).unwrap();
+        // the last peak of each replicate has no partner
+        assert_eq!(result.len(), 40);
+        let model = result.model();
+        assert!(model.rho() > 0.5 && model.rho() <= IDR_MAX_RHO);
+        assert!(model.sigma() >= IDR_MIN_SIGMA);
+        assert!(model.mix() > 0.2 && model.mix() < 0.8);
+
+        // global IDR is the running mean of the sorted local IDR
+        let mut cumulative = 0.0;
+        for (i,x) in result.iter().enumerate() {
+            cumulative += x.local_idr();
+            assert_abs_diff_eq!(x.global_idr(), cumulative / (i + 1) as f64, epsilon=1e-12);
+            if i > 0 {
+                assert!(x.local_idr() >= result[i - 1].local_idr());
+            }
+        }
+
+        // the strongly correlated top half of the peaks is reproducible, the rest is noise
+        let first = result.iter()
+            .find(|x| x.rep1().name() == "peak_1")
+            .unwrap();
+        assert_eq!((first.peak().start(), first.peak().end()), (1000, 1250));
+        assert_eq!(first.peak().summit(), 1110);
+        assert!(first.local_idr() < 0.05);
+        let passing = result.passing(0.05);
+        assert!(!passing.is_empty());
+        for x in result.iter().filter(|x| x.local_idr() <= 0.05) {
+            let rank: usize = x.rep1().name()[5..].parse().unwrap();
+            assert!(rank <= 20);
+        }
+        let noise_idr = mean(&result.iter()
+            .filter(|x| x.rep1().name()[5..].parse::<usize>().unwrap() > 20)
+            .map(|x| x.local_idr())
+            .collect::<Vec<f64>>()).unwrap();
+        assert!(noise_idr > 0.5);
+    }
+
+    #[test]
+    fn test_correlation() {
+        let a = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 10, 1.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 20, 2.0),
+            BEDGraphRecord::new("chrA".to_string(), 20, 30, 3.0),
+            BEDGraphRecord::new("chrA".to_string(), 30, 40, 0.0),
+            BEDGraphRecord::new("chrA".to_string(), 40, 50, 10.0),
+        ]);
+        let b = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 10, 2.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 20, 4.0),
+            BEDGraphRecord::new("chrA".to_string(), 20, 30, 6.0),
+            BEDGraphRecord::new("chrA".to_string(), 30, 40, 0.0),
+            BEDGraphRecord::new("chrA".to_string(), 40, 50, 5.0),
+        ]);
+        // c covers only the first three bins
+        let c = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 30, 1.0),
+        ]);
+        let x = [1.0, 2.0, 3.0, 0.0, 10.0];
+        let y = [2.0, 4.0, 6.0, 0.0, 5.0];
+        let filter = CorrelationFilter::default();
+        assert_abs_diff_eq!(
+            a.correlation(&b, Correlation::Pearson, &filter).unwrap(),
+            pearson(&x, &y),
+            epsilon=1e-12,
+        );
+        // ranks are identical apart from the last two bins
+        assert_abs_diff_eq!(
+            a.correlation(&b, Correlation::Spearman, &filter).unwrap(),
+            1.0 - 6.0 * 2.0 / (5.0 * 24.0),
+            epsilon=1e-12,
+        );
+
+        let skip_zeros = CorrelationFilter { skip_zeros: true, ..Default::default() };
+        assert_abs_diff_eq!(
+            a.correlation(&b, Correlation::Spearman, &skip_zeros).unwrap(),
+            0.8,
+            epsilon=1e-12,
+        );
+        let regions = BEDData::from_record_vec(vec![
+            BEDRecord::new("chrA".to_string(), 5, 25),
+        ]);
+        let blacklist = BEDData::from_record_vec(vec![
+            BEDRecord::new("chrA".to_string(), 20, 22),
+        ]);
+        let restricted = CorrelationFilter {
+            regions: Some(&regions),
+            blacklist: Some(&blacklist),
+            skip_zeros: false,
+        };
+        // only the first two bins remain
+        assert_abs_diff_eq!(
+            a.correlation(&b, Correlation::Pearson, &restricted).unwrap(),
+            1.0,
+            epsilon=1e-12,
+        );
+
+        let matrix = correlation_matrix(
+            &[&a, &b, &c],
+            &["a", "b", "c"],
+            Correlation::Pearson,
+            &filter,
+        ).unwrap();
+        assert_eq!((matrix.nrow(), matrix.ncol()), (3, 3));
+        assert_abs_diff_eq!(matrix.get(1, 0), matrix.get(0, 1), epsilon=1e-12);
+        assert_eq!(matrix.get(0, 0), 1.0);
+        // c is constant, so has no correlation even with itself
+        assert!(matrix.get(2, 2).is_nan());
+        assert!(matrix.get(0, 2).is_nan());
+    }
+
+    #[test]
+    fn test_summary() {
+        let bgd = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 10, 1.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 40, 4.0),
+            BEDGraphRecord::new("chrA".to_string(), 40, 50, f64::NAN),
+            BEDGraphRecord::new("chrB".to_string(), 0, 10, 2.0),
+        ]);
+        let summary = bgd.summary(&[0.0, 0.5, 1.0], false).unwrap();
+        assert_eq!(summary.label(), "genome");
+        assert_eq!((summary.count(), summary.nan_count(), summary.covered()), (3, 1, 50));
+        assert_abs_diff_eq!(summary.mean(), 7.0 / 3.0, epsilon=1e-12);
+        assert_abs_diff_eq!(summary.sd(), std_dev(&[1.0, 4.0, 2.0]).unwrap(), epsilon=1e-12);
+        assert_eq!((summary.min(), summary.max()), (1.0, 4.0));
+        assert_eq!(summary.quantiles(), &[(0.0, 1.0), (0.5, 2.0), (1.0, 4.0)]);
+
+        // weighting by width, as if each base pair were a value
+        let weighted = bgd.summary(&[0.25, 0.5], true).unwrap();
+        let mut per_base: Vec<f64> = vec![1.0; 10];
+        per_base.extend(vec![4.0; 30]);
+        per_base.extend(vec![2.0; 10]);
+        assert_abs_diff_eq!(weighted.mean(), mean(&per_base).unwrap(), epsilon=1e-12);
+        assert_abs_diff_eq!(weighted.sd(), std_dev(&per_base).unwrap(), epsilon=1e-12);
+        per_base.sort_by(|a, b| a.total_cmp(b));
+        assert_abs_diff_eq!(
+            weighted.quantiles()[0].1,
+            quantile_sorted(&per_base, 0.25),
+            epsilon=1e-12,
+        );
+        assert_abs_diff_eq!(weighted.quantiles()[1].1, 4.0, epsilon=1e-12);
+
+        let contigs = bgd.contig_summaries(&[0.5], false).unwrap();
+        assert_eq!(contigs.len(), 2);
+        assert_eq!(contigs[0].label(), "chrA");
+        assert_abs_diff_eq!(contigs[0].quantiles()[0].1, 2.5, epsilon=1e-12);
+        assert_eq!(contigs[1].count(), 1);
+        assert!(contigs[1].sd().is_nan());
+        assert!(bgd.summary(&[1.5], false).is_err());
+
+        // median no longer panics when scores include NaN
+        assert!(bgd.median(NanPolicy::Propagate).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let bgd = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 10, 0.0),
+            BEDGraphRecord::new("chrA".to_string(), 10, 20, 1.0),
+            BEDGraphRecord::new("chrA".to_string(), 20, 50, 2.0),
+            BEDGraphRecord::new("chrA".to_string(), 50, 60, 2.0),
+            BEDGraphRecord::new("chrA".to_string(), 60, 70, f64::NAN),
+            BEDGraphRecord::new("chrA".to_string(), 70, 80, 4.0),
+        ]);
+        let hist = bgd.histogram(HistogramBins::Fixed(4), false).unwrap();
+        assert_eq!(hist.edges(), &[0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(hist.counts(), &[1, 1, 2, 1]);
+        let hist = bgd.histogram(HistogramBins::Fixed(2), true).unwrap();
+        assert_eq!(hist.counts(), &[20, 50]);
+
+        let hist = bgd.histogram(HistogramBins::Quantile(4), false).unwrap();
+        assert_eq!(hist.edges(), &[0.0, 1.0, 2.0, 4.0]);
+        assert_eq!(hist.counts(), &[1, 1, 3]);
+
+        // IQR of 1, so bins of width 2 / 5^(1/3)
+        let hist = bgd.histogram(HistogramBins::FreedmanDiaconis, false).unwrap();
+        assert_eq!(hist.n_bins(), 4);
+        assert_eq!(hist.counts().iter().sum::<usize>(), 5);
+        assert!(bgd.histogram(HistogramBins::Fixed(0), false).is_err());
+
+        // a near-zero IQR and an outlier would call for ~10^9 bins, but the count
+        // is capped at the number of records; a zero IQR falls back to Sturges' rule
+        let mut records: Vec<BEDGraphRecord> = (0..8)
+            .map(|i| BEDGraphRecord::new("chrA".to_string(), i, i + 1, 1.0 + i as f64 * 1e-9))
+            .collect();
+        records.push(BEDGraphRecord::new("chrA".to_string(), 8, 9, 1e3));
+        let spiky = BEDGraphData::from_record_vec(records);
+        let hist = spiky.histogram(HistogramBins::FreedmanDiaconis, false).unwrap();
+        assert_eq!(hist.n_bins(), 9);
+        let flat = BEDGraphData::from_record_vec(vec![
+            BEDGraphRecord::new("chrA".to_string(), 0, 8, 1.0),
+            BEDGraphRecord::new("chrA".to_string(), 8, 9, 5.0),
+        ]);
+        let hist = flat.histogram(HistogramBins::FreedmanDiaconis, true).unwrap();
+        assert_eq!(hist.n_bins(), 5);
+        assert_eq!(hist.counts(), &[8, 0, 0, 0, 1]);
+
+        let ecdf = bgd.ecdf(false).unwrap();
+        assert_abs_diff_eq!(ecdf.cdf(-1.0), 0.0);
+        assert_abs_diff_eq!(ecdf.cdf(2.0), 0.8);
+        assert_abs_diff_eq!(ecdf.cdf(3.0), 0.8);
+        assert_abs_diff_eq!(ecdf.cdf(4.0), 1.0);
+        assert_abs_diff_eq!(ecdf.quantile(0.5).unwrap(), 2.0);
+        assert_abs_diff_eq!(ecdf.quantile(0.0).unwrap(), 0.0);
+        assert_abs_diff_eq!(ecdf.quantile(0.9).unwrap(), 4.0);
+        let ecdf = bgd.ecdf(true).unwrap();
+        assert_abs_diff_eq!(ecdf.cdf(1.0), 2.0 / 7.0);
+        assert!(ecdf.quantile(1.5).is_err());
+    }
+
+    #[test]
+    fn test_missing_scores() {
+        let bgd = BEDGraphData::from_file_with_missing(
+            &path::Path::new(TESTDIR).join("missing.bedgraph"),
+            &["-"],
+        ).unwrap();
+        let scores = bgd.fetch_scores().unwrap();
+        assert_eq!(scores.iter().filter(|x| x.is_nan()).count(), 5);
+        assert_eq!(scores[4], 5.0);
+
+        assert_eq!(bgd.median(NanPolicy::Skip).unwrap(), 5.0);
+        assert_eq!(bgd.mean(NanPolicy::Skip).unwrap(), 5.0);
+        assert_eq!(bgd.mad(NanPolicy::Skip).unwrap(), 2.0);
+        assert!(bgd.median(NanPolicy::Propagate).unwrap().is_nan());
+        assert!(bgd.mean(NanPolicy::Propagate).unwrap().is_nan());
+
+        // every window of three records holds a missing score
+        for func in [RollFn::Mean, RollFn::Median] {
+            let skipped = bgd.roll_fn(3, false, func, NanPolicy::Skip).unwrap();
+            for i in 1..9 {
+                assert_abs_diff_eq!(skipped[i].score, (i + 1) as f64, epsilon=1e-12);
+            }
+        }
+        let propagated = bgd.roll_fn(3, false, RollFn::Sum, NanPolicy::Propagate).unwrap();
+        assert!(propagated.fetch_scores().unwrap().iter().all(|x| x.is_nan()));
+
+        let skipped = bgd.roll_fn_bp(30, WindowAlign::Center, false, 0.5, RollFn::Mean, NanPolicy::Skip).unwrap();
+        assert_abs_diff_eq!(skipped[1].score, 2.0, epsilon=1e-12);
+        let skipped = bgd.roll_fn_bp(30, WindowAlign::Center, false, 0.7, RollFn::Mean, NanPolicy::Skip).unwrap();
+        assert!(skipped[1].score.is_nan());
+
+        let z = bgd.z_score(ZScore::Standard, &ZScope::Genome, NanPolicy::Skip).unwrap();
+        assert_abs_diff_eq!(z[0].score, -4.0 / 10_f64.sqrt(), epsilon=1e-12);
+        assert!(z[1].score.is_nan());
+        let z = bgd.z_score(ZScore::Standard, &ZScope::Genome, NanPolicy::Propagate).unwrap();
+        assert!(z[0].score.is_nan());
+        let z = bgd.standard_z(NanPolicy::Skip).unwrap();
+        assert_abs_diff_eq!(z[0].score, -4.0 / 10_f64.sqrt(), epsilon=1e-12);
+        let z = bgd.robust_z(NanPolicy::Skip).unwrap();
+        assert_abs_diff_eq!(z[0].score, -4.0 / (MAD_SCALE * 2.0), epsilon=1e-12);
+        for z in [bgd.standard_z(NanPolicy::Propagate), bgd.robust_z(NanPolicy::Propagate)] {
+            assert!(z.unwrap().fetch_scores().unwrap().iter().all(|x| x.is_nan()));
+        }
+
+        // missing bases are uncovered when skipped, and poison the region when propagated
+        let regions = BEDData::from_record_vec(vec![BEDRecord::new("chrA".to_string(), 0, 40)]);
+        let summary = &bgd.region_summary(&regions, NanPolicy::Skip).unwrap()[0];
+        assert_eq!(summary.covered(), 20);
+        assert_eq!((summary.sum(), summary.mean_region()), (40.0, 1.0));
+        assert_eq!((summary.min(), summary.max(), summary.median()), (1.0, 3.0, 2.0));
+        let summary = &bgd.region_summary(&regions, NanPolicy::Propagate).unwrap()[0];
+        assert_eq!(summary.covered(), 40);
+        assert!(summary.sum().is_nan() && summary.max().is_nan() && summary.median().is_nan());
+
+        // every aggregation treats missing scores the same way
+        for agg in [Aggregation::Mean, Aggregation::Max, Aggregation::Median] {
+            let skipped = bgd.rebin(20, agg, f64::NAN, NanPolicy::Skip).unwrap();
+            assert_eq!(skipped.fetch_scores().unwrap(), vec![1.0, 3.0, 5.0, 7.0, 9.0]);
+            let propagated = bgd.rebin(20, agg, 0.0, NanPolicy::Propagate).unwrap();
+            assert!(propagated.fetch_scores().unwrap().iter().all(|x| x.is_nan()));
+        }
+
+        // the reference distribution is 1.5, 2.5, 3.5, and the two present scores of
+        // the first track take its lowest and highest values
+        let tracks = [
+            BEDGraphData::from_record_vec(vec![
+                BEDGraphRecord::new("chrA".to_string(), 0, 5, 2.0),
+                BEDGraphRecord::new("chrA".to_string(), 5, 10, f64::NAN),
+                BEDGraphRecord::new("chrA".to_string(), 10, 15, 4.0),
+            ]),
+            BEDGraphData::from_record_vec(vec![
+                BEDGraphRecord::new("chrA".to_string(), 0, 5, 1.0),
+                BEDGraphRecord::new("chrA".to_string(), 5, 10, 2.0),
+                BEDGraphRecord::new("chrA".to_string(), 10, 15, 3.0),
+            ]),
+        ];
+        let normalized = get_quantile_normalized(&[&tracks[0], &tracks[1]], NanPolicy::Skip).unwrap();
+        assert_eq!((normalized[0][0], normalized[0][2]), (1.5, 3.5));
+        assert!(normalized[0][1].is_nan());
+        assert_eq!(normalized[1], vec![1.5, 2.5, 3.5]);
+        let normalized = get_quantile_normalized(&[&tracks[0], &tracks[1]], NanPolicy::Propagate).unwrap();
+        assert!(normalized.iter().flatten().all(|x| x.is_nan()));
+
+        // a constant track with one missing score
+        let records: Vec<BEDGraphRecord> = (0..20)
+            .map(|i| {
+                let score = if i == 10 { f64::NAN } else { 2.0 };
+                BEDGraphRecord::new("chrA".to_string(), i * 10, (i + 1) * 10, score)
+            }).collect();
+        let bgd = BEDGraphData::from_record_vec(records);
+        for smoother in [
+            Smoother::Gaussian { sigma_bp: 10.0 },
+            Smoother::Exponential { half_life_bp: 10.0 },
+        ] {
+            let skipped = bgd.smooth(&smoother, false, NanPolicy::Skip).unwrap();
+            for score in skipped.fetch_scores().unwrap() {
+                assert_abs_diff_eq!(score, 2.0, epsilon=1e-9);
+            }
+            let propagated = bgd.smooth(&smoother, false, NanPolicy::Propagate).unwrap();
+            assert!(propagated[10].score.is_nan());
+            assert!(!propagated[0].score.is_nan());
+        }
+        let sg = Smoother::SavitzkyGolay { window_size: 5, order: 2, deriv: 0 };
+        assert!(bgd.smooth(&sg, false, NanPolicy::Skip).is_err());
+    }
+
+    #[test]
+    fn test_enrichment() {
+        let genome = Genome::from_contig_vec(vec![
+            Contig::new("chrA".to_string(), 10_000, false),
+            Contig::new("plasmid".to_string(), 500, true),
+        ]);
+        let features = BEDData::from_record_vec(
+            (0..10).map(|i| BEDRecord::new("chrA".to_string(), i * 1000, i * 1000 + 100)).collect()
+        );
+        let exclude = BEDData::from_record_vec(vec![
+            BEDRecord::new("chrA".to_string(), 5_000, 10_000),
+        ]);
+
+        let shuffled = features.shuffle(&genome, Some(&exclude), true, 7).unwrap();
+        assert_eq!(shuffled.len(), 10);
+        for record in shuffled.iter() {
+            assert_eq!(record.seqname(), "chrA");
+            assert_eq!(record.end() - record.start(), 100);
+            assert!(record.end() <= 5_000);
+        }
+        assert_eq!(
+            shuffled.iter().map(|x| x.start()).collect::<Vec<usize>>(),
+            features.shuffle(&genome, Some(&exclude), true, 7).unwrap()
+                .iter().map(|x| x.start()).collect::<Vec<usize>>(),
+        );
+        // records placed across the origin of the plasmid are split, keeping their width
+        let on_plasmid = BEDData::from_record_vec(
+            (0..20).map(|_| BEDRecord::new("plasmid".to_string(), 0, 200)).collect()
+        );
+        let shuffled = on_plasmid.shuffle(&genome, None, true, 3).unwrap();
+        assert!(shuffled.len() > 20);
+        assert_eq!(shuffled.iter().map(|x| x.end() - x.start()).sum::<usize>(), 4_000);
+        let too_wide = BEDData::from_record_vec(vec![
+            BEDRecord::new("chrA".to_string(), 0, 6_000),
+        ]);
+        assert!(too_wide.shuffle(&genome, Some(&exclude), true, 0).is_err());
+
+        let params = EnrichmentParams { n_permutations: 200, ..Default::default() };
+        let result = features.enrichment(&features, &genome, &params).unwrap();
+        assert_eq!(result.observed(), 10.0);
+        assert_eq!(result.null().len(), 200);
+        assert_abs_diff_eq!(result.p_value(), 1.0 / 201.0);
+        assert!(result.fold_enrichment() > 5.0);
+        assert!(result.z_score() > 5.0);
+
+        let threaded = EnrichmentParams { n_threads: 3, ..params.clone() };
+        assert_eq!(features.enrichment(&features, &genome, &threaded).unwrap(), result);
+
+        let bp = EnrichmentParams { measure: OverlapMeasure::Bp, ..params };
+        let result = features.enrichment(&features, &genome, &bp).unwrap();
+        assert_eq!(result.observed(), 1_000.0);
+        let expected_bp = 10.0 * 100.0 * 1_000.0 / 10_500.0;
+        assert!((result.expected() - expected_bp).abs() < 0.25 * expected_bp);
+    }
+
+    #[test]
+    fn test_overlap_stats() {
+        let a = BEDData::from_record_vec(vec![
+            BEDRecord::new("chrA".to_string(), 0, 100),
+            BEDRecord::new("chrA".to_string(), 50, 150),
+            BEDRecord::new("chrA".to_string(), 400, 500),
+            BEDRecord::new("chrB".to_string(), 0, 100),
+        ]);
+        let b = BEDData::from_record_vec(vec![
+            BEDRecord::new("chrA".to_string(), 100, 200),
+            BEDRecord::new("chrB".to_string(), 300, 400),
+        ]);
+        // 50 bp shared, out of 350 + 200 - 50 covered by either
+        assert_abs_diff_eq!(a.jaccard(&b), 50.0 / 500.0, epsilon=1e-12);
+        assert_abs_diff_eq!(a.jaccard(&a), 1.0, epsilon=1e-12);
+        let empty = BEDData::from_record_vec(Vec::new());
+        assert!(empty.jaccard(&empty).is_nan());
+
+        let matrix = jaccard_matrix(&[&a, &b, &empty], &["a", "b", "empty"]).unwrap();
+        assert_eq!((matrix.nrow(), matrix.ncol()), (3, 3));
+        assert_abs_diff_eq!(matrix.get(1, 0), 0.1, epsilon=1e-12);
+        assert_abs_diff_eq!(matrix.get(0, 2), 0.0, epsilon=1e-12);
+        assert_abs_diff_eq!(matrix.get(1, 1), 1.0, epsilon=1e-12);
+        assert!(matrix.get(2, 2).is_nan());
+
+        let genome = Genome::from_contig_vec(vec![
+            Contig::new("chrA".to_string(), 1_000, false),
+            Contig::new("chrB".to_string(), 1_000, false),
+        ]);
+        let result = a.fisher(&b, &genome).unwrap();
+        // merged widths of 150, 100, 100, 100, and 100 bp, for a mean of 110;
+        // 1500 bp are covered by neither set
+        assert_eq!(result.table(), [[1, 2], [1, 14]]);
+        assert_abs_diff_eq!(result.odds_ratio(), 7.0, epsilon=1e-12);
+        // hypergeometric probabilities of 0, 1, and 2 shared intervals
+        let p0 = 1.0 * 15.0 * 14.0 / 2.0 / (18.0 * 17.0 / 2.0);
+        let p1 = 3.0 * 15.0 / (18.0 * 17.0 / 2.0);
+        let p2 = 3.0 / (18.0 * 17.0 / 2.0);
+        assert_abs_diff_eq!(p0 + p1 + p2, 1.0, epsilon=1e-12);
+        assert_abs_diff_eq!(result.p_right(), p1 + p2, epsilon=1e-9);
+        assert_abs_diff_eq!(result.p_left(), p0 + p1, epsilon=1e-9);
+        assert_abs_diff_eq!(result.p_two_tailed(), p1 + p2, epsilon=1e-9);
+
+        let chr_a = Genome::from_contig_vec(vec![Contig::new("chrA".to_string(), 1_000, false)]);
+        assert!(a.fisher(&b, &chr_a).is_err());
+    }
+
+    #[test]
+    fn test_annotate_regions() {
+        let gff = GffData::from_file(&path::Path::new(TESTDIR).join("genes.gff3")).unwrap();
+        assert_eq!(gff.len(), 12);
+        assert_eq!((gff[0].start(), gff[0].end()), (100, 400));
+        assert_eq!(gff[8].phase(), Some(0));
+        assert_eq!(gff[8].attribute("Parent"), Some("rna-g3"));
+        let genes = gff.gene_models();
+        assert_eq!(genes.len(), 4);
+        assert_eq!(genes[2].name(), "g3");
+        assert_eq!(genes[2].tss(), 1599);
+        assert_eq!(gff[10].attribute("Note"), Some("tRNA-Ala, anticodon=UGC; 100% identity"));
+
+        let gtf = GffData::from_file(&path::Path::new(TESTDIR).join("genes.gtf")).unwrap();
+        assert_eq!(gtf[0].attribute("note"), Some("a=b"));
+        assert_eq!(gtf[1].attribute("note"), Some("a; b"));
+        assert_eq!(gtf[1].attribute("gene_name"), Some("g3"));
+        assert_eq!(percent_decode("%zz 50% %2"), "%zz 50% %2");
+        assert_eq!(GffFormat::detect("gene_id \"g=1\";"), GffFormat::Gtf);
+        assert_eq!(GffFormat::detect("Name=a b"), GffFormat::Gff3);
+        let gtf_genes = gtf.gene_models();
+        assert_eq!(gtf_genes.len(), 1);
+        assert_eq!((gtf_genes[0].start(), gtf_genes[0].end()), (1000, 1600));
+        assert_eq!(gtf_genes[0].strand(), Strand::Reverse);
+
+        let positions = [70, 410, 450, 1100, 1300, 1575, 1020, 2030, 5000];
+        let regions = BEDData::from_record_vec(
+            positions.iter().map(|x| BEDRecord::new("chrA".to_string(), x - 5, x + 5)).collect()
+        );
+        let params = AnnotationParams {
+            promoter_upstream: 300,
+            promoter_downstream: 0,
+            operon_gap: None,
+        };
+        let annotated = regions.annotate_regions(&gff, &params);
+        let contexts: Vec<GenomicContext> = annotated.iter().map(|x| x.context()).collect();
+        assert_eq!(contexts, vec![
+            GenomicContext::Promoter,
+            GenomicContext::Promoter,
+            GenomicContext::Cds,
+            GenomicContext::Cds,
+            GenomicContext::Intron,
+            GenomicContext::FivePrimeUtr,
+            GenomicContext::ThreePrimeUtr,
+            GenomicContext::NonCoding,
+            GenomicContext::Intergenic,
+        ]);
+        let nearest: Vec<&str> = annotated.iter().map(|x| x.gene().unwrap().name()).collect();
+        assert_eq!(nearest, vec!["g1", "g2", "g2", "g3", "g3", "g3", "g3", "g4", "g4"]);
+        assert_eq!(annotated[0].tss_distance(), Some(-30));
+        assert_eq!(annotated[4].tss_distance(), Some(299));
+        assert_eq!(annotated[8].tss_distance(), Some(3000));
+        assert_eq!(
+            format!("{}", annotated[0]),
+            "chrA\t65\t75\t.\t.\t.\tpromoter\tgene-g1\tg1\t+\t-30\tg1\n",
+        );
+
+        // g1 and g2 form an operon transcribed from the promoter of g1
+        let params = AnnotationParams { operon_gap: Some(50), ..params };
+        let annotated = regions.annotate_regions(&gff, &params);
+        assert_eq!(annotated[1].context(), GenomicContext::Intergenic);
+        assert_eq!(annotated[2].gene().unwrap().name(), "g1");
+        assert_eq!(annotated[2].tss_distance(), Some(350));
+        assert_eq!(annotated[2].operon(), &["g1".to_string(), "g2".to_string()]);
+    }
+
+    #[test]
+    fn test_robust_z() {
+        let bgd = BEDGraphData::from_file(
+            &path::Path::new(TESTDIR).join("small.bedgraph"),
+        ).unwrap();
+        let rz = bgd.robust_z(NanPolicy::Skip).unwrap();
+        let answer: Vec<f64> = vec![
+            0.684522,
+            0.684522,
+            0.684522,
+            -0.356128,
+            -0.674491,
+            -0.145361,
+            -1.22207,
+            0.0,
+            0.324074,
+        ];
+        let zscores = rz.fetch_scores().unwrap();
+        for (i,res) in zscores.iter().enumerate() {
+            assert_abs_diff_eq!(*res, answer[i], epsilon=1e-5);
+        }
+
+        let flat = bgd.filter("CP064350.1", &0, &usize::MAX).unwrap();
+        assert!(flat.robust_z(NanPolicy::Skip).is_err());
+    }
+
+    #[test]
+    fn test_dispersion() {
+        let vals = vec![1.0, 2.0, 3.0, 4.0, 100.0];
+        assert_abs_diff_eq!(mad(&vals).unwrap(), 1.0, epsilon=1e-12);
+        assert_abs_diff_eq!(scaled_mad(&vals).unwrap(), MAD_SCALE, epsilon=1e-12);
+        assert_abs_diff_eq!(iqr(&vals).unwrap(), 2.0, epsilon=1e-12);
+        assert_abs_diff_eq!(std_dev(&[1.0, 2.0, 3.0]).unwrap(), 1.0, epsilon=1e-12);
+        // pairwise distances sorted are 1, 1, 1, 2, 2, 3, 96, ...; h = 3, so k = 3
+        assert_abs_diff_eq!(qn(&vals).unwrap(), 2.2219, epsilon=1e-12);
+        // inner medians are 2, 1, 1, 2, 97
+        assert_abs_diff_eq!(sn(&vals).unwrap(), 2.0 * 1.1926, epsilon=1e-12);
+        assert_abs_diff_eq!(Dispersion::Iqr.sigma(&vals).unwrap(), 2.0 / 1.349, epsilon=1e-12);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BEDGraphRecordNotDivisible {
+    start: usize,
+    end: usize,
+    width: usize,
+}
+
+// Implement the Display trait for BEDGraphRecordNotDivisible
+impl fmt::Display for BEDGraphRecordNotDivisible {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BEDGraphRecord width is not evenly divisible: start = {}, end = {}, desired width = {}. Exiting without printing results.",
+            self.start, self.end, self.width
+        )
+    }
+}
+
+// Implement the Error trait for BEDGraphRecordNotDivisible. Since we don't have any additional
+// data to provide, we can just defer to the Display implementation.
+impl Error for BEDGraphRecordNotDivisible {}
+
+#[derive(Debug, Clone)]
+struct ContigNotFound {
+    seqname: String,
+}
+
+impl fmt::Display for ContigNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Contig {} was not found in the genome.",
+            self.seqname,
+        )
+    }
+}
+
+impl Error for ContigNotFound {}
+
+#[derive(Debug, Clone)]
+struct ZeroDispersion {
+    dispersion: Dispersion,
+}
+
+impl fmt::Display for ZeroDispersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Scores have zero dispersion ({:?}), so their z-scores are undefined.",
+            self.dispersion,
+        )
+    }
+}
+
+impl Error for ZeroDispersion {}
+
+#[derive(Debug, Clone)]
+struct InvalidParameter {
+    message: String,
+}
+
+impl fmt::Display for InvalidParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid parameter: {}", self.message)
+    }
+}
+
+impl Error for InvalidParameter {}
+
+/// f64 ordered by [f64::total_cmp], so that scores can be kept in a [BinaryHeap]
+#[derive(Debug, Clone, Copy)]
+struct TotalF64(f64);
+
+impl PartialEq for TotalF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalF64 {}
+
+impl PartialOrd for TotalF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Tracks the kth and (k+1)th smallest values (zero-based) of a sliding window.
+///
+/// The k+1 smallest values are kept in a max-heap, `low`, and the rest in a min-heap,
+/// `high`, so that the kth value is the top of `low` and the (k+1)th the top of `high`.
+/// Values leaving the window are deleted lazily: they are counted in `delayed` and only
+/// popped once they reach the top of a heap, so that each update takes O(log w) time.
+/// `low_size` and `high_size` count the values in each heap that are still in the window.
+struct RollingOrderStat {
+    k: usize,
+    low: BinaryHeap<TotalF64>,
+    high: BinaryHeap<Reverse<TotalF64>>,
+    delayed: HashMap<u64, usize>,
+    low_size: usize,
+    high_size: usize,
+}
+
+impl RollingOrderStat {
+    fn new(k: usize) -> RollingOrderStat {
+        RollingOrderStat {
+            k,
+            low: BinaryHeap::new(),
+            high: BinaryHeap::new(),
+            delayed: HashMap::new(),
+            low_size: 0,
+            high_size: 0,
+        }
+    }
+
+    fn insert(&mut self, x: f64) {
+        match self.low.peek() {
+            Some(top) if TotalF64(x) > *top => {
+                self.high.push(Reverse(TotalF64(x)));
+                self.high_size += 1;
+            },
+            _ => {
+                self.low.push(TotalF64(x));
+                self.low_size += 1;
+            },
+        }
+        self.rebalance();
+    }
+
+    fn remove(&mut self, x: f64) {
+        *self.delayed.entry(x.to_bits()).or_insert(0) += 1;
+        match self.low.peek() {
+            Some(top) if TotalF64(x) <= *top => {
+                self.low_size -= 1;
+                self.prune_low();
+            },
+            _ => {
+                self.high_size -= 1;
+                self.prune_high();
+            },
+        }
+        self.rebalance();
+    }
+
+    /// returns true, and consumes one pending deletion, if x has been removed from the window
+    fn take_delayed(&mut self, x: f64) -> bool {
+        let key = x.to_bits();
+        match self.delayed.get_mut(&key) {
+            Some(count) => {
+                *count -= 1;
+                // drop spent entries so the map only holds values still awaiting removal
+                if *count == 0 {
+                    self.delayed.remove(&key);
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn prune_low(&mut self) {
+        while let Some(top) = self.low.peek().copied() {
+            if !self.take_delayed(top.0) {
+                break;
+            }
+            self.low.pop();
+        }
+    }
+
+    fn prune_high(&mut self) {
+        while let Some(Reverse(top)) = self.high.peek().copied() {
+            if !self.take_delayed(top.0) {
+                break;
+            }
+            self.high.pop();
+        }
+    }
+
+    /// moves values between the heaps until `low` holds exactly k+1 live values
+    fn rebalance(&mut self) {
+        while self.low_size > self.k + 1 {
+            let top = self.low.pop().unwrap();
+            self.high.push(Reverse(top));
+            self.low_size -= 1;
+            self.high_size += 1;
+            self.prune_low();
+        }
+        while self.low_size < self.k + 1 && self.high_size > 0 {
+            let Reverse(top) = self.high.pop().unwrap();
+            self.low.push(top);
+            self.high_size -= 1;
+            self.low_size += 1;
+            self.prune_high();
+        }
+    }
+
+    /// changes k, for windows whose size varies
+    fn set_k(&mut self, k: usize) {
+        self.k = k;
+        self.rebalance();
+    }
+
+    fn kth(&self) -> f64 {
+        self.low.peek().map_or(f64::NAN, |x| x.0)
+    }
+
+    fn next_after_kth(&self) -> f64 {
+        self.high.peek().map_or(f64::NAN, |x| x.0.0)
+    }
+}
+
+/// returns, for each window of window_size consecutive values, the kth smallest value
+/// (zero-based) plus frac of the distance to the (k+1)th, in O(n log w) time
+fn rolling_order_stat(values: &[f64], window_size: usize, k: usize, frac: f64) -> Vec<f64> {
+    let mut results: Vec<f64> = Vec::with_capacity(values.len());
+    if window_size == 0 || values.len() < window_size {
+        return results;
+    }
+    let mut stat = RollingOrderStat::new(k);
+    for (i,x) in values.iter().enumerate() {
+        stat.insert(*x);
+        if i >= window_size {
+            stat.remove(values[i - window_size]);
+        }
+        if i + 1 >= window_size {
+            let kth = stat.kth();
+            if frac > 0.0 {
+                results.push(kth + frac * (stat.next_after_kth() - kth));
+            } else {
+                results.push(kth);
+            }
+        }
+    }
+    results
+}
+
+/// returns the maximum (or minimum, if max is false) of each window of window_size
+/// consecutive values, using a monotonic deque of candidate positions in O(n) time
+fn rolling_extreme(values: &[f64], window_size: usize, max: bool) -> Vec<f64> {
+    let mut results: Vec<f64> = Vec::with_capacity(values.len());
+    if window_size == 0 {
+        return results;
+    }
+    let dominates = |a: f64, b: f64| if max { a >= b } else { a <= b };
+    let mut candidates: VecDeque<usize> = VecDeque::new();
+    for (i,x) in values.iter().enumerate() {
+        while let Some(back) = candidates.back() {
+            if !dominates(*x, values[*back]) {
+                break;
+            }
+            candidates.pop_back();
+        }
+        candidates.push_back(i);
+        if candidates[0] + window_size <= i {
+            candidates.pop_front();
+        }
+        if i + 1 >= window_size {
+            results.push(values[candidates[0]]);
+        }
+    }
+    results
+}
+
+
+/// Applies a [RollFn] to a window that slides forward through values, adding and
+/// removing each value once as the window's bounds advance. Sums and variances are kept
+/// as running totals, medians and quantiles by a [RollingOrderStat] whose k follows the
+/// window's size, and minima and maxima by monotonic deques of candidate positions, which
+/// also let the variance of a window of equal values be exactly zero. Missing values are
+/// only counted, and custom functions are applied to each window in turn.
+struct RollingWindow<'a> {
+    values: &'a [f64],
+    func: &'a RollFn,
+    nan_policy: NanPolicy,
+    first: usize,
+    last: usize,
+    n_missing: usize,
+    n_present: usize,
+    n_pos_inf: usize,
+    n_neg_inf: usize,
+    sum: f64,
+    // Welford's running mean and sum of squared deviations of the finite values
+    n_finite: usize,
+    finite_mean: f64,
+    m2: f64,
+    order: RollingOrderStat,
+    minima: VecDeque<usize>,
+    maxima: VecDeque<usize>,
+}
+
+impl<'a> RollingWindow<'a> {
+    fn new(
+            values: &'a [f64],
+            func: &'a RollFn,
+            nan_policy: NanPolicy,
+    ) -> Result<RollingWindow<'a>, Box<dyn Error>> {
+        if let RollFn::Quantile(p) = func {
+            if !(0.0..=1.0).contains(p) {
+                return Err(Box::new(InvalidParameter {
+                    message: format!("quantile must be between 0 and 1, but {} was given", p),
+                }));
+            }
+        }
+        Ok(RollingWindow {
+            values,
+            func,
+            nan_policy,
+            first: 0,
+            last: 0,
+            n_missing: 0,
+            n_present: 0,
+            n_pos_inf: 0,
+            n_neg_inf: 0,
+            sum: 0.0,
+            n_finite: 0,
+            finite_mean: 0.0,
+            m2: 0.0,
+            order: RollingOrderStat::new(0),
+            minima: VecDeque::new(),
+            maxima: VecDeque::new(),
+        })
+    }
+
+    /// moves the window to values[first..last]. Bounds are expected not to decrease,
+    /// but if they do, the window is rebuilt from scratch.
+    fn advance(&mut self, first: usize, last: usize) -> Result<(), Box<dyn Error>> {
+        if first < self.first || last < self.last {
+            *self = RollingWindow::new(self.values, self.func, self.nan_policy)?;
+        }
+        while self.last < last {
+            self.add(self.last);
+            self.last += 1;
+        }
+        while self.first < first {
+            self.remove(self.first);
+            self.first += 1;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, i: usize) {
+        let x = self.values[i];
+        if x.is_nan() {
+            self.n_missing += 1;
+            return;
+        }
+        self.n_present += 1;
+        match self.func {
+            RollFn::Mean | RollFn::Sum | RollFn::Variance | RollFn::Sd => {
+                if x == f64::INFINITY {
+                    self.n_pos_inf += 1;
+                } else if x == f64::NEG_INFINITY {
+                    self.n_neg_inf += 1;
+                } else {
+                    self.sum += x;
+                    self.n_finite += 1;
+                    let delta = x - self.finite_mean;
+                    self.finite_mean += delta / self.n_finite as f64;
+                    self.m2 += delta * (x - self.finite_mean);
+                }
+            },
+            RollFn::Median | RollFn::Quantile(_) => {
+                self.order.insert(x);
+                self.order.set_k(self.order_k().0);
+            },
+            RollFn::Min | RollFn::Max | RollFn::Custom(_) => {},
+        }
+        if matches!(self.func, RollFn::Min | RollFn::Variance | RollFn::Sd) {
+            while self.minima.back().is_some_and(|back| x <= self.values[*back]) {
+                self.minima.pop_back();
+            }
+            self.minima.push_back(i);
+        }
+        if matches!(self.func, RollFn::Max | RollFn::Variance | RollFn::Sd) {
+            while self.maxima.back().is_some_and(|back| x >= self.values[*back]) {
+                self.maxima.pop_back();
+            }
+            self.maxima.push_back(i);
+        }
+    }
+
+    fn remove(&mut self, i: usize) {
+        let x = self.values[i];
+        if x.is_nan() {
+            self.n_missing -= 1;
+            return;
+        }
+        self.n_present -= 1;
+        match self.func {
+            RollFn::Mean | RollFn::Sum | RollFn::Variance | RollFn::Sd => {
+                if x == f64::INFINITY {
+                    self.n_pos_inf -= 1;
+                } else if x == f64::NEG_INFINITY {
+                    self.n_neg_inf -= 1;
+                } else {
+                    self.sum -= x;
+                    self.n_finite -= 1;
+                    if self.n_finite == 0 {
+                        self.sum = 0.0;
+                        self.finite_mean = 0.0;
+                        self.m2 = 0.0;
+                    } else {
+                        let delta = x - self.finite_mean;
+                        self.finite_mean -= delta / self.n_finite as f64;
+                        self.m2 = (self.m2 - delta * (x - self.finite_mean)).max(0.0);
+                        // a single value has no spread, whatever rounding has accumulated
+                        if self.n_finite == 1 {
+                            self.m2 = 0.0;
+                        }
+                    }
+                }
+            },
+            RollFn::Median | RollFn::Quantile(_) => {
+                self.order.remove(x);
+                self.order.set_k(self.order_k().0);
+            },
+            RollFn::Min | RollFn::Max | RollFn::Custom(_) => {},
+        }
+        if self.minima.front() == Some(&i) {
+            self.minima.pop_front();
+        }
+        if self.maxima.front() == Some(&i) {
+            self.maxima.pop_front();
+        }
+    }
+
+    /// the zero-based order statistic, and the fraction of the distance to the next,
+    /// giving the median or quantile of the values present
+    fn order_k(&self) -> (usize, f64) {
+        let n = self.n_present;
+        match self.func {
+            RollFn::Median if n > 0 && n.is_multiple_of(2) => (n / 2 - 1, 0.5),
+            RollFn::Median => (n / 2, 0.0),
+            RollFn::Quantile(p) => {
+                let h = p * n.saturating_sub(1) as f64;
+                (h.floor() as usize, h.fract())
+            },
+            _ => (0, 0.0),
+        }
+    }
+
+    /// the function's value over the current window, handling missing values as given
+    /// by nan_policy. The result is NaN if the window holds no values.
+    fn value(&self) -> Result<f64, Box<dyn Error>> {
+        if self.n_present == 0 || (self.nan_policy == NanPolicy::Propagate && self.n_missing > 0) {
+            return Ok(f64::NAN);
+        }
+        let infinite_sum = match (self.n_pos_inf > 0, self.n_neg_inf > 0) {
+            (true, true) => Some(f64::NAN),
+            (true, false) => Some(f64::INFINITY),
+            (false, true) => Some(f64::NEG_INFINITY),
+            (false, false) => None,
+        };
+        let value = match self.func {
+            RollFn::Sum => infinite_sum.unwrap_or(self.sum),
+            RollFn::Mean => infinite_sum.unwrap_or(self.sum / self.n_present as f64),
+            RollFn::Variance | RollFn::Sd => {
+                let var = if infinite_sum.is_some() {
+                    f64::NAN
+                } else if self.n_present > 1 && self.values[self.minima[0]] == self.values[self.maxima[0]] {
+                    0.0
+                } else {
+                    self.m2 / (self.n_present as f64 - 1.0)
+                };
+                if let RollFn::Sd = self.func { var.sqrt() } else { var }
+            },
+            RollFn::Median => {
+                let kth = self.order.kth();
+                if self.n_present.is_multiple_of(2) {
+                    (kth + self.order.next_after_kth()) / 2.0
+                } else {
+                    kth
+                }
+            },
+            RollFn::Quantile(_) => {
+                let (_, frac) = self.order_k();
+                let kth = self.order.kth();
+                if frac > 0.0 {
+                    kth + frac * (self.order.next_after_kth() - kth)
+                } else {
+                    kth
+                }
+            },
+            RollFn::Min => self.values[self.minima[0]],
+            RollFn::Max => self.values[self.maxima[0]],
+            RollFn::Custom(_) => {
+                return self.func.apply(&self.values[self.first..self.last], self.nan_policy);
+            },
+        };
+        Ok(value)
+    }
+}
+/// natural log of the gamma function, by the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        std::f64::consts::PI.ln() - (std::f64::consts::PI * x).sin().ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let t = x + 7.5;
+        let series = COEFS[1..].iter()
+            .enumerate()
+            .fold(COEFS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+        0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+    }
+}
+
+/// -log10 of the Poisson probability of observing k or more events given mean lambda.
+/// The upper tail is summed in log space, so that very small p-values do not underflow.
+fn poisson_mlog10_sf(k: u64, lambda: f64) -> f64 {
+    if k == 0 {
+        return 0.0;
+    }
+    if lambda <= 0.0 {
+        return f64::INFINITY;
+    }
+    let ln_lambda = lambda.ln();
+    let log_p = if k as f64 > lambda {
+        let mut log_term = k as f64 * ln_lambda - lambda - ln_gamma(k as f64 + 1.0);
+        let mut log_sum = log_term;
+        let mut i = k;
+        loop {
+            i += 1;
+            log_term += ln_lambda - (i as f64).ln();
+            let hi = log_sum.max(log_term);
+            log_sum = hi + ((log_sum - hi).exp() + (log_term - hi).exp()).ln();
+            if log_term - log_sum < -40.0 {
+                break;
+            }
+        }
+        log_sum
+    } else {
+        let cdf: f64 = (0..k)
+            .map(|i| (i as f64 * ln_lambda - lambda - ln_gamma(i as f64 + 1.0)).exp())
+            .sum();
+        (1.0 - cdf).max(f64::MIN_POSITIVE).ln()
+    };
+    (-log_p / std::f64::consts::LN_10).max(0.0)
+}
+
+/// standard normal cumulative distribution function, using the Chebyshev
+/// approximation of erfc with fractional error below 1.2e-7
+fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let erfc = t * (-z * z - 1.265_512_23 + t * (1.000_023_68 + t * (0.374_091_96
+        + t * (0.096_784_18 + t * (-0.186_288_06 + t * (0.278_868_07 + t * (-1.135_203_98
+        + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))))).exp();
+    if x >= 0.0 {
+        1.0 - 0.5 * erfc
+    } else {
+        0.5 * erfc
+    }
+}
+
+/// standard normal probability density function
+fn normal_pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// ranks of the values, from 1 for the smallest, with ties given their average rank
+fn average_ranks(values: &[f64]) -> Vec<f64> {
+    let order: Vec<usize> = (0..values.len())
+        .sorted_by(|a, b| values[*a].total_cmp(&values[*b]))
+        .collect();
+    let mut ranks = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        for idx in &order[i..=j] {
+            ranks[*idx] = rank;
+        }
+        i = j + 1;
+    }
+    ranks
+}
+
+/// multiple testing correction applied by [p_adjust]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PAdjust {
+    /// false discovery rate control of Benjamini and Hochberg (1995)
+    BenjaminiHochberg,
+    /// false discovery rate control of Benjamini and Yekutieli (2001), valid under
+    /// arbitrary dependence between tests
+    BenjaminiYekutieli,
+    /// family-wise error rate control by multiplying p-values by the number of tests
+    Bonferroni,
+    /// family-wise error rate control by Holm's (1979) step-down procedure
+    Holm,
+    /// q-values of Storey (2002), which scale Benjamini-Hochberg adjusted values by the
+    /// estimated proportion of true nulls, pi0 = #{p > lambda} / (n * (1 - lambda)).
+    /// Returns an error if no p-values exceed lambda, making pi0 zero.
+    Storey {
+        lambda: f64,
+    },
+}
+
+/// adjusts p-values for multiple testing, returning the adjusted values.
+/// NaN p-values are excluded from the number of tests and remain NaN.
+pub fn p_adjust(p_values: &[f64], method: PAdjust) -> Result<Vec<f64>, Box<dyn Error>> {
+    if p_values.iter().any(|x| *x < 0.0 || *x > 1.0) {
+        return Err(Box::new(InvalidParameter {
+            message: String::from("p-values must be between 0 and 1"),
+        }));
+    }
+    let mlog10_p: Vec<f64> = p_values.iter().map(|x| -x.log10()).collect();
+    let mlog10_q = p_adjust_mlog10(&mlog10_p, method)?;
+    Ok(mlog10_q.iter().map(|x| 10_f64.powf(-x)).collect())
+}
+
+/// adjusts p-values given as -log10 values for multiple testing, returning -log10
+/// adjusted values. Working with logarithms keeps p-values too small to represent
+/// as f64 from underflowing to zero. See [p_adjust].
+pub fn p_adjust_mlog10(mlog10_p: &[f64], method: PAdjust) -> Result<Vec<f64>, Box<dyn Error>> {
+    if let PAdjust::Storey { lambda } = method {
+        if !(0.0..1.0).contains(&lambda) {
+            return Err(Box::new(InvalidParameter {
+                message: format!("Storey's lambda must be in [0, 1), but {} was given", lambda),
+            }));
+        }
+    }
+    // log10 p-values of the tests, in ascending order of p-value
+    let order: Vec<usize> = (0..mlog10_p.len())
+        .filter(|i| !mlog10_p[*i].is_nan())
+        .sorted_by(|a, b| mlog10_p[*b].total_cmp(&mlog10_p[*a]))
+        .collect();
+    let n = order.len() as f64;
+    let log10_p: Vec<f64> = order.iter().map(|i| -mlog10_p[*i]).collect();
+    let mut log10_q: Vec<f64> = vec![0.0; order.len()];
+
+    match method {
+        PAdjust::Bonferroni => {
+            for (rank,p) in log10_p.iter().enumerate() {
+                log10_q[rank] = p + n.log10();
+            }
+        },
+        PAdjust::Holm => {
+            let mut running_max = f64::NEG_INFINITY;
+            for (rank,p) in log10_p.iter().enumerate() {
+                running_max = running_max.max(p + (n - rank as f64).log10());
+                log10_q[rank] = running_max;
+            }
+        },
+        PAdjust::BenjaminiHochberg | PAdjust::BenjaminiYekutieli | PAdjust::Storey { .. } => {
+            let log10_factor = match method {
+                PAdjust::BenjaminiYekutieli => {
+                    (1..=order.len()).map(|i| 1.0 / i as f64).sum::<f64>().log10()
+                },
+                PAdjust::Storey { lambda } => {
+                    let n_null = log10_p.iter().filter(|x| 10_f64.powf(**x) > lambda).count();
+                    // as in the qvalue package, an estimate of no true nulls is an error,
+                    // rather than q-values of zero
+                    if n_null == 0 && !order.is_empty() {
+                        return Err(Box::new(InvalidParameter {
+                            message: format!("no p-values exceed Storey's lambda of {}, so pi0 is zero", lambda),
+                        }));
+                    }
+                    (n_null as f64 / (n * (1.0 - lambda))).min(1.0).log10()
+                },
+                _ => 0.0,
+            };
+            let mut running_min = f64::INFINITY;
+            for (rank,p) in log10_p.iter().enumerate().rev() {
+                running_min = running_min.min(p + (n / (rank + 1) as f64).log10() + log10_factor);
+                log10_q[rank] = running_min;
+            }
+        },
+    }
+
+    let mut mlog10_q = vec![f64::NAN; mlog10_p.len()];
+    for (rank,i) in order.iter().enumerate() {
+        mlog10_q[*i] = -log10_q[rank].min(0.0);
+    }
+    Ok(mlog10_q)
+}
+
+/// position of each record within the window used to calculate its rolling value
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WindowAlign {
+    /// the window is centered on the record
+    Center,
+    /// the window starts at the record and extends downstream of it
+    Left,
+    /// the window ends at the record and extends upstream of it
+    Right,
+}
+
+/// smoothing filter applied by [BEDGraphData::smooth]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Smoother {
+    /// Gaussian kernel with standard deviation sigma_bp, truncated at four standard deviations
+    Gaussian {
+        sigma_bp: f64,
+    },
+    /// triangular kernel whose weights fall linearly to zero half_width_bp from its center
+    Triangular {
+        half_width_bp: usize,
+    },
+    /// Savitzky-Golay filter fitting a polynomial of the given order by least squares
+    /// over an odd-sized window of window_size records. A nonzero deriv gives that
+    /// derivative of the fitted polynomial, per bp, instead of its value.
+    SavitzkyGolay {
+        window_size: usize,
+        order: usize,
+        deriv: usize,
+    },
+    /// exponential moving average, running from the start of each contig, in which
+    /// the weight of each score halves every half_life_bp
+    Exponential {
+        half_life_bp: f64,
+    },
+}
+
+impl Smoother {
+    /// returns convolution weights over an odd number of records,
+    /// given the width of each record in bp
+    fn kernel(&self, resolution: f64) -> Result<Vec<f64>, Box<dyn Error>> {
+        self.check(resolution)?;
+        let weights: Vec<f64> = match self {
+            Smoother::Gaussian { sigma_bp } => {
+                let sigma = sigma_bp / resolution;
+                let half = (4.0 * sigma).ceil() as i64;
+                (-half..=half)
+                    .map(|k| (-(k * k) as f64 / (2.0 * sigma * sigma)).exp())
+                    .collect()
+            },
+            Smoother::Triangular { half_width_bp } => {
+                let half = (*half_width_bp as f64 / resolution).floor() as i64;
+                (-half..=half)
+                    .map(|k| (half + 1 - k.abs()) as f64)
+                    .collect()
+            },
+            Smoother::SavitzkyGolay { window_size, order, deriv } => {
+                let coefs = savgol_coefficients(*window_size, *order, *deriv)?;
+                let per_bp = resolution.powi(*deriv as i32);
+                return Ok(coefs.iter().map(|x| x / per_bp).collect());
+            },
+            Smoother::Exponential { .. } => vec![1.0],
+        };
+        let total: f64 = weights.iter().sum();
+        Ok(weights.iter().map(|x| x / total).collect())
+    }
+
+    /// returns an error if the smoother's width is not positive, or, for a triangular
+    /// kernel, too narrow to span more than one record of the given width in bp
+    fn check(&self, resolution: f64) -> Result<(), Box<dyn Error>> {
+        let message = match self {
+            Smoother::Gaussian { sigma_bp } if !(*sigma_bp > 0.0 && sigma_bp.is_finite()) => {
+                format!("Gaussian smoothing needs a positive sigma, but {} was given", sigma_bp)
+            },
+            Smoother::Triangular { half_width_bp } if (*half_width_bp as f64) < resolution => {
+                format!(
+                    "triangular smoothing needs a half width of at least one record ({} bp), but {} was given",
+                    resolution,
+                    half_width_bp,
+                )
+            },
+            Smoother::Exponential { half_life_bp } if !(*half_life_bp > 0.0 && half_life_bp.is_finite()) => {
+                format!("exponential smoothing needs a positive half life, but {} was given", half_life_bp)
+            },
+            _ => return Ok(()),
+        };
+        Err(Box::new(InvalidParameter { message }))
+    }
+}
+
+/// returns the Savitzky-Golay convolution coefficients giving the deriv-th derivative,
+/// per record, of a polynomial of the given order fit over window_size records
+fn savgol_coefficients(
+        window_size: usize,
+        order: usize,
+        deriv: usize,
+) -> Result<Vec<f64>, Box<dyn Error>> {
+    if window_size.is_multiple_of(2) || order >= window_size || deriv > order {
+        return Err(Box::new(InvalidParameter {
+            message: format!(
+                "Savitzky-Golay filters need an odd window size greater than the polynomial order, and a derivative no greater than the order, but window size {}, order {}, and derivative {} were given",
+                window_size,
+                order,
+                deriv,
+            ),
+        }));
+    }
+    let half = (window_size / 2) as i64;
+    let m = order + 1;
+    // normal equations of the least-squares fit, augmented with the unit vector for deriv,
+    // so that elimination gives row deriv of their inverse
+    let mut aug: Vec<Vec<f64>> = (0..m).map(|a| {
+        let mut row: Vec<f64> = (0..m)
+            .map(|b| (-half..=half).map(|i| (i as f64).powi((a + b) as i32)).sum())
+            .collect();
+        row.push(if a == deriv { 1.0 } else { 0.0 });
+        row
+    }).collect();
+    for col in 0..m {
+        let pivot = (col..m)
+            .max_by(|x, y| aug[*x][col].abs().partial_cmp(&aug[*y][col].abs()).unwrap())
+            .unwrap();
+        aug.swap(col, pivot);
+        let pivot_row = aug[col].clone();
+        for (row, row_vals) in aug.iter_mut().enumerate() {
+            if row != col {
+                let factor = row_vals[col] / pivot_row[col];
+                for (x, p) in row_vals[col..].iter_mut().zip(pivot_row[col..].iter()) {
+                    *x -= factor * p;
+                }
+            }
+        }
+    }
+    let inv_row: Vec<f64> = (0..m).map(|b| aug[b][m] / aug[b][b]).collect();
+    let deriv_factorial: f64 = (1..=deriv).map(|x| x as f64).product();
+    Ok((-half..=half).map(|i| {
+        deriv_factorial * inv_row.iter()
+            .enumerate()
+            .map(|(b, y)| y * (i as f64).powi(b as i32))
+            .sum::<f64>()
+    }).collect())
+}
+
+/// user-supplied function of the scores in a window
+pub type WindowFn = Box<dyn Fn(&[f64]) -> f64>;
+
+/// function applied to each window by [BEDGraphData::roll_fn]
+pub enum RollFn {
+    Median,
+    Mean,
+    Sum,
+    Min,
+    Max,
+    /// the given quantile, between 0 and 1, interpolating linearly between order statistics
+    Quantile(f64),
+    /// sample variance
+    Variance,
+    /// sample standard deviation
+    Sd,
+    /// any function of the scores in the window, which is called with each window's
+    /// scores in full, rather than updated as windows slide
+    Custom(WindowFn),
+}
+
+impl RollFn {
+    /// applies self to the scores of a single window, handling missing scores
+    /// as given by nan_policy
+    fn apply(&self, window: &[f64], nan_policy: NanPolicy) -> Result<f64, Box<dyn Error>> {
+        match nan_policy.select(window) {
+            Some(mut values) => self.apply_complete(&mut values),
+            None => Ok(f64::NAN),
+        }
+    }
+
+    /// applies self to the scores of a single window with no missing scores,
+    /// which may be reordered
+    fn apply_complete(&self, window: &mut [f64]) -> Result<f64, Box<dyn Error>> {
+        let value = match self {
+            RollFn::Median => median(window)?,
+            RollFn::Mean => mean(window)?,
+            RollFn::Sum => window.iter().sum(),
+            RollFn::Min => window.iter().copied().fold(f64::INFINITY, f64::min),
+            RollFn::Max => window.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            RollFn::Quantile(p) => {
+                if !(0.0..=1.0).contains(p) {
+                    return Err(Box::new(InvalidParameter {
+                        message: format!("quantile must be between 0 and 1, but {} was given", p),
+                    }));
+                }
+                window.sort_by(|a, b| a.total_cmp(b));
+                quantile_sorted(window, *p)
+            },
+            RollFn::Variance => std_dev(window)?.powi(2),
+            RollFn::Sd => std_dev(window)?,
+            RollFn::Custom(func) => func(window),
+        };
+        Ok(value)
+    }
+
+    /// applies self to each window of window_size consecutive values in padded_scores,
+    /// handling missing scores as given by nan_policy. Windows without missing scores
+    /// use the running computations of [RollFn::roll_complete], and the rest are
+    /// evaluated one by one.
+    fn roll(
+            &self,
+            padded_scores: &[f64],
+            window_size: usize,
+            nan_policy: NanPolicy,
+    ) -> Result<Vec<f64>, Box<dyn Error>> {
+        if !padded_scores.iter().any(|x| x.is_nan()) {
+            return self.roll_complete(padded_scores, window_size);
+        }
+        let filled: Vec<f64> = padded_scores.iter()
+            .map(|x| if x.is_nan() { 0.0 } else { *x })
+            .collect();
+        let mut results = self.roll_complete(&filled, window_size)?;
+        let has_nan = nan_windows(padded_scores, window_size);
+        for (i,window) in padded_scores.windows(window_size).enumerate() {
+            if has_nan[i] {
+                results[i] = self.apply(window, nan_policy)?;
+            }
+        }
+        Ok(results)
+    }
+
+    /// applies self to each window of window_size consecutive values in padded_scores,
+    /// none of which may be missing
+    fn roll_complete(&self, padded_scores: &[f64], window_size: usize) -> Result<Vec<f64>, Box<dyn Error>> {
+        let win_size_f = window_size as f64;
+        let windows = padded_scores.windows(window_size);
+        let results: Vec<f64> = match self {
+            RollFn::Median => {
+                if window_size.is_multiple_of(2) {
+                    rolling_order_stat(padded_scores, window_size, window_size / 2 - 1, 0.5)
+                } else {
+                    rolling_order_stat(padded_scores, window_size, window_size / 2, 0.0)
+                }
+            },
+            RollFn::Mean | RollFn::Sum => {
+                let mut sums: Vec<f64> = Vec::with_capacity(padded_scores.len());
+                let mut sum_prev_opt: Option<(f64, f64)> = None;
+
+                for window in windows {
+                    let sum = match sum_prev_opt {
+                        None => {
+                            window.iter().sum::<f64>()
+                        },
+                        Some((prev_sum, prev)) => {
+                            let next = window.last().unwrap();
+                            prev_sum + (*next - prev)
+                        }
+                    };
+                    let prev = window.first().unwrap();
+                    sums.push(sum);
+                    sum_prev_opt = Some((sum, *prev))
+                }
+                if let RollFn::Mean = self {
+                    sums.iter().map(|x| x / win_size_f).collect()
+                } else {
+                    sums
+                }
+            },
+            RollFn::Min => rolling_extreme(padded_scores, window_size, false),
+            RollFn::Max => rolling_extreme(padded_scores, window_size, true),
+            RollFn::Quantile(p) => {
+                if !(0.0..=1.0).contains(p) {
+                    return Err(Box::new(InvalidParameter {
+                        message: format!("quantile must be between 0 and 1, but {} was given", p),
+                    }));
+                }
+                let h = p * (window_size - 1) as f64;
+                rolling_order_stat(padded_scores, window_size, h.floor() as usize, h.fract())
+            },
+            RollFn::Variance | RollFn::Sd => {
+                let mut results: Vec<f64> = Vec::with_capacity(padded_scores.len());
+                for window in windows {
+                    let var = std_dev(window)?.powi(2);
+                    results.push(if let RollFn::Sd = self { var.sqrt() } else { var });
+                }
+                results
+            },
+            RollFn::Custom(func) => windows.map(func).collect(),
+        };
+        Ok(results)
+    }
+}
+
+/// how missing scores, represented as NaN, are handled by statistics and rolling functions
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NanPolicy {
+    /// missing scores are ignored, and a result is NaN only if all its scores are missing
+    Skip,
+    /// any missing score makes the result NaN
+    Propagate,
+}
+
+impl NanPolicy {
+    /// sums values, leaving out missing values if they are skipped
+    fn sum(&self, values: impl Iterator<Item = f64>) -> f64 {
+        match self {
+            NanPolicy::Skip => values.filter(|x| !x.is_nan()).sum(),
+            NanPolicy::Propagate => values.sum(),
+        }
+    }
+
+    /// returns the values from which to compute a statistic, or None if it is NaN
+    fn select(&self, values: &[f64]) -> Option<Vec<f64>> {
+        if let NanPolicy::Propagate = self {
+            if values.iter().any(|x| x.is_nan()) {
+                return None;
+            }
+        }
+        let kept: Vec<f64> = values.iter().copied().filter(|x| !x.is_nan()).collect();
+        if kept.is_empty() { None } else { Some(kept) }
+    }
+}
+
+/// for each window of window_size consecutive values, true if it holds any NaN
+fn nan_windows(values: &[f64], window_size: usize) -> Vec<bool> {
+    let mut n_nan = 0;
+    let mut results: Vec<bool> = Vec::with_capacity(values.len());
+    for (i,x) in values.iter().enumerate() {
+        if x.is_nan() {
+            n_nan += 1;
+        }
+        if i >= window_size && values[i - window_size].is_nan() {
+            n_nan -= 1;
+        }
+        if i + 1 >= window_size {
+            results.push(n_nan > 0);
+        }
+    }
+    results
+}
+
+/// median of vec, which is sorted in place. Like [mean], returns NaN if
+/// vec is empty or contains NaN.
+fn median(vec: &mut [f64]) -> Result<f64, Box<dyn Error>> {
+    if vec.is_empty() || vec.iter().any(|x| x.is_nan()) {
+        return Ok(f64::NAN);
+    }
+    vec.sort_by(|a, b| a.total_cmp(b));
+    let length = vec.len();
+    let mid = length / 2;
+    if length % 2 == 1 {
+        Ok(vec[mid])
+    } else {
+        let median = (vec[mid-1] + vec[mid]) / 2.0;
+        Ok(median)
+    }
+}
+
+fn mean(vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+    let sum: f64 = vec.iter().sum();
+    let count = vec.len() as f64;
+    Ok(sum / count)
+}
+
+/// constant relating the median absolute deviation to the standard
+/// deviation of normally distributed data
+pub const MAD_SCALE: f64 = 1.4826;
+
+/// constant relating the interquartile range to the standard
+/// deviation of normally distributed data
+const IQR_SCALE: f64 = 1.349;
+
+/// quantile p of sorted (value, width) pairs, as if each value were repeated once per
+/// base pair of its width, linearly interpolating between order statistics
+fn weighted_quantile_sorted(sorted: &[(f64, usize)], p: f64) -> f64 {
+    let cumulative: Vec<usize> = sorted.iter()
+        .scan(0, |acc, x| {
+            *acc += x.1;
+            Some(*acc)
+        }).collect();
+    let total = match cumulative.last() {
+        Some(total) if *total > 0 => *total,
+        _ => return f64::NAN,
+    };
+    let value_at = |base: usize| sorted[cumulative.partition_point(|x| *x <= base)].0;
+    let h = p * (total - 1) as f64;
+    let lo = h.floor() as usize;
+    let hi = h.ceil() as usize;
+    value_at(lo) + (h - lo as f64) * (value_at(hi) - value_at(lo))
+}
+
+/// quantile p of sorted values, linearly interpolating between order statistics
+fn quantile_sorted(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let h = p * (sorted.len() - 1) as f64;
+    let lo = h.floor() as usize;
+    let hi = h.ceil() as usize;
+    sorted[lo] + (h - lo as f64) * (sorted[hi] - sorted[lo])
+}
+
+/// returns an error if any of probs lies outside [0, 1]
+fn check_probabilities(probs: &[f64]) -> Result<(), Box<dyn Error>> {
+    match probs.iter().find(|p| !(0.0..=1.0).contains(*p)) {
+        Some(p) => Err(Box::new(InvalidParameter {
+            message: format!("probabilities must be between 0 and 1, but {} was given", p),
+        })),
+        None => Ok(()),
+    }
+}
+
+/// median absolute deviation of vec from its median
+pub fn mad(vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+    let mut scores = vec.to_vec();
+    let median_score = median(&mut scores)?;
+    let mut abs_devs: Vec<f64> = scores.iter()
+        .map(|a| (a-median_score).abs())
+        .collect();
+    let mad = median(&mut abs_devs)?;
+    Ok(mad)
+}
+
+/// median absolute deviation multiplied by [MAD_SCALE], making it a consistent
+/// estimator of the standard deviation for normally distributed data
+pub fn scaled_mad(vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+    Ok(MAD_SCALE * mad(vec)?)
+}
+
+/// interquartile range of vec, using linear interpolation between order statistics
+pub fn iqr(vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+    let mut sorted = vec.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    Ok(quantile_sorted(&sorted, 0.75) - quantile_sorted(&sorted, 0.25))
+}
+
+/// sample standard deviation of vec
+pub fn std_dev(vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+    let mean_score = mean(vec)?;
+    let ss: f64 = vec.iter().map(|x| (x - mean_score).powi(2)).sum();
+    Ok((ss / (vec.len() as f64 - 1.0)).sqrt())
+}
+
+/// Qn scale estimator of Rousseeuw and Croux (1993): the kth smallest pairwise
+/// distance between values, where k = h choose 2 and h = n / 2 + 1, multiplied
+/// by 2.2219 for consistency with the standard deviation of normal data.
+/// Small-sample correction factors are not applied. Runs in quadratic time,
+/// so is best suited to small groups of records.
+pub fn qn(vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+    let n = vec.len();
+    if n < 2 {
+        return Ok(f64::NAN);
+    }
+    let mut dists: Vec<f64> = Vec::with_capacity(n * (n - 1) / 2);
+    for i in 0..n {
+        for j in (i+1)..n {
+            dists.push((vec[i] - vec[j]).abs());
+        }
+    }
+    let h = n / 2 + 1;
+    let k = h * (h - 1) / 2;
+    let (_, kth, _) = dists.select_nth_unstable_by(k - 1, |a, b| a.total_cmp(b));
+    Ok(2.2219 * *kth)
+}
+
+/// Sn scale estimator of Rousseeuw and Croux (1993): the median over values of the
+/// median distance to every other value, multiplied by 1.1926 for consistency with
+/// the standard deviation of normal data. Small-sample correction factors are not
+/// applied. Runs in quadratic time, so is best suited to small groups of records.
+pub fn sn(vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+    let mut inner_medians: Vec<f64> = Vec::with_capacity(vec.len());
+    for x in vec {
+        let mut dists: Vec<f64> = vec.iter().map(|y| (x - y).abs()).collect();
+        inner_medians.push(median(&mut dists)?);
+    }
+    Ok(1.1926 * median(&mut inner_medians)?)
+}
+
+/// estimator of the spread of scores, as used by [BEDGraphData::robust_z_with]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dispersion {
+    /// median absolute deviation from the median, see [mad]
+    Mad,
+    /// see [scaled_mad]
+    ScaledMad,
+    /// see [iqr]
+    Iqr,
+    /// see [std_dev]
+    Sd,
+    /// see [qn]
+    Qn,
+    /// see [sn]
+    Sn,
+}
+
+impl Dispersion {
+    /// returns the value of this estimator for vec
+    pub fn estimate(&self, vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+        match self {
+            Dispersion::Mad => mad(vec),
+            Dispersion::ScaledMad => scaled_mad(vec),
+            Dispersion::Iqr => iqr(vec),
+            Dispersion::Sd => std_dev(vec),
+            Dispersion::Qn => qn(vec),
+            Dispersion::Sn => sn(vec),
+        }
+    }
+
+    /// returns this estimator for vec, scaled to estimate the standard deviation of
+    /// normally distributed data. The MAD and scaled MAD give the same value here.
+    pub fn sigma(&self, vec: &[f64]) -> Result<f64, Box<dyn Error>> {
+        match self {
+            Dispersion::Mad => scaled_mad(vec),
+            Dispersion::Iqr => Ok(iqr(vec)? / IQR_SCALE),
+            _ => self.estimate(vec),
+        }
+    }
+}
+
+/// robust z-score of x, given the median of the scores and their spread
+/// as an estimate of the standard deviation
+fn robust_z(x: f64, median: f64, sigma: f64) -> f64 {
+    (x - median) / sigma
+}
+
+/// median of scores, each weighted by a number of base pairs.
+/// When the cumulative weight falls exactly halfway between two scores,
+/// their mean is returned, matching [median] for equal weights.
+/// Scores with zero weight are ignored, and NaN is returned if no score has any weight.
+fn weighted_median(pairs: &mut [(f64, usize)]) -> f64 {
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: usize = pairs.iter().map(|x| x.1).sum();
+    if total == 0 {
+        return f64::NAN;
+    }
+    let mut weighted = pairs.iter().filter(|x| x.1 > 0);
+    let mut cumulative: usize = 0;
+    while let Some((score, weight)) = weighted.next() {
+        cumulative += weight;
+        if cumulative * 2 == total {
+            // the cumulative weight can only reach half of a positive total
+            // before the last weighted score, so a next one always exists
+            let next = weighted.next().map_or(*score, |x| x.0);
+            return (score + next) / 2.0;
+        } else if cumulative * 2 > total {
+            return *score;
+        }
+    }
+    f64::NAN
+}
+
+/// strand of a stranded record, as given in the sixth column of a bed file
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum Strand {
+    #[serde(rename = "+")]
+    Forward,
+    #[serde(rename = "-")]
+    Reverse,
+    #[serde(rename = ".", alias = "?")]
+    Unknown,
+}
+
+/// Implement `Display` for `Strand`.
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Strand::Forward => "+",
+            Strand::Reverse => "-",
+            Strand::Unknown => ".",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// end of a record that an operation should be anchored to
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Anchor {
+    /// the 5' end of the record, which is the end position for reverse-strand records
+    FivePrime,
+    /// the 3' end of the record, which is the start position for reverse-strand records
+    ThreePrime,
+    /// the midpoint of the record
+    Center,
+}
+
+/// tokens read as missing scores, represented as NaN, in bedgraph score columns
+pub const MISSING_TOKENS: [&str; 4] = ["NaN", "NA", "nan", "."];
+
+/// parses a score, reading any of missing_tokens as NaN
+fn parse_score(field: &str, missing_tokens: &[&str]) -> Result<f64, std::num::ParseFloatError> {
+    if missing_tokens.contains(&field) {
+        Ok(f64::NAN)
+    } else {
+        field.parse::<f64>()
+    }
+}
+
+/// deserializes a bedgraph score, reading any of [MISSING_TOKENS] as NaN
+fn deserialize_score<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let field = String::deserialize(deserializer)?;
+    parse_score(&field, &MISSING_TOKENS).map_err(serde::de::Error::custom)
+}
+
+/// parses an optional bed score column, treating "." as missing
+fn deserialize_optional_score<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let field: Option<String> = Option::deserialize(deserializer)?;
+    match field.as_deref() {
+        None | Some(".") => Ok(None),
+        Some(val) => val.parse::<f64>()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// struct to define a single line of a bed file
+///
+/// The name, score, and strand columns are optional, so that both
+/// three-column and six-column bed files can be read.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct BEDRecord {
+    seqname: String,
+    start: usize,
+    end: usize,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_score")]
+    score: Option<f64>,
+    #[serde(default)]
+    strand: Option<Strand>,
+}
+
+impl BEDRecord {
+    pub fn new(
+        seqname: String,
+        start: usize,
+        end: usize,
+    ) -> BEDRecord {
+        BEDRecord {
+            seqname,
+            start,
+            end,
+            name: None,
+            score: None,
+            strand: None,
+        }
+    }
+
+    pub fn new_stranded(
+        seqname: String,
+        start: usize,
+        end: usize,
+        name: Option<String>,
+        score: Option<f64>,
+        strand: Strand,
+    ) -> BEDRecord {
+        BEDRecord {
+            seqname,
+            start,
+            end,
+            name,
+            score,
+            strand: Some(strand),
+        }
+    }
+
+    pub fn seqname(&self) -> &str {
+        &self.seqname
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+
+    /// returns the record's strand, with records lacking a strand column
+    /// treated as [Strand::Unknown]
+    pub fn strand(&self) -> Strand {
+        self.strand.unwrap_or(Strand::Unknown)
+    }
+
+    fn is_reverse(&self) -> bool {
+        self.strand() == Strand::Reverse
+    }
+
+    fn set(&mut self, seqname: &str, start: &usize, end: &usize) {
+        self.set_seqname(seqname);
+        self.set_start(start);
+        self.set_end(end);
+    }
+
+    fn set_seqname(&mut self, seqname: &str) {
+        self.seqname = String::from(seqname);
+    }
+
+    fn set_start(&mut self, start: &usize) {
+        self.start = *start;
+    }
+
+    fn set_end(&mut self, end: &usize) {
+        self.end = *end;
+    }
+
+    /// places the interval [start, end), which may run off either end of its contig,
+    /// onto the contig, copying name, score, and strand from self.
+    ///
+    /// On linear contigs the interval is clipped to the contig bounds. On circular
+    /// contigs it is wrapped around the origin, and split into two records if it
+    /// spans the origin. Intervals that lie entirely off a linear contig yield no records.
+    fn place(
+            &self,
+            start: i64,
+            end: i64,
+            genome: &Genome,
+    ) -> Result<Vec<BEDRecord>, Box<dyn Error>> {
+        let contig = genome.get_contig(&self.seqname)?;
+        let ctg_len = contig.length as i64;
+        let mut records = Vec::new();
+        let mut push = |s: i64, e: i64| {
+            let mut rec = self.clone();
+            rec.set_start(&(s as usize));
+            rec.set_end(&(e as usize));
+            records.push(rec);
+        };
+
+        if contig.circular {
+            if end - start >= ctg_len {
+                push(0, ctg_len);
+            } else {
+                let wrapped_start = start.rem_euclid(ctg_len);
+                let wrapped_end = wrapped_start + (end - start);
+                if wrapped_end <= ctg_len {
+                    push(wrapped_start, wrapped_end);
+                } else {
+                    push(wrapped_start, ctg_len);
+                    push(0, wrapped_end - ctg_len);
+                }
+            }
+        } else {
+            let clipped_start = start.max(0);
+            let clipped_end = end.min(ctg_len);
+            if clipped_start < clipped_end {
+                push(clipped_start, clipped_end);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// struct to define a single line of a bedgraph file
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct BEDGraphRecord {
+    seqname: String,
+    start: usize,
+    end: usize,
+    #[serde(deserialize_with = "deserialize_score")]
+    score: f64,
+}
+
+impl BEDGraphRecord {
+    pub fn new(
+        seqname: String,
+        start: usize,
+        end: usize,
+        score: f64,
+    ) -> BEDGraphRecord {
+        BEDGraphRecord {
+            seqname,
+            start,
+            end,
+            score,
+        }
+    }
+
+    fn set_score(&mut self, new_score: f64) {
+        self.score = new_score;
+    }
+
+    fn width(&self) -> usize {
+        self.end - self.start
+    }
+
+    // This method attempts to split the BEDGraphRecord into several with equal width 'w'.
+    fn split_evenly(
+            &self,
+            w: usize,
             final_end: usize,
     ) -> Result<Vec<BEDGraphRecord>, BEDGraphRecordNotDivisible> {
         let width = self.end - self.start;
         let is_final_record = self.end == final_end;
 
-        // Check if the width is evenly divisible by w.
-        if width % w == 0 || is_final_record {
-            // Calculate the number of records that will be produced.
-            let num_full_width_records = width / w;
-            // Create the new records.
-            let mut records = Vec::with_capacity(num_full_width_records+1);
-            for i in 0..num_full_width_records {
-                records.push(BEDGraphRecord {
-                    seqname: self.seqname.to_string(),
-                    start: self.start + i * w,
-                    end: self.start + (i + 1) * w,
-                    score: self.score,
-                });
+        // Check if the width is evenly divisible by w.
+        if width.is_multiple_of(w) || is_final_record {
+            // Calculate the number of records that will be produced.
+            let num_full_width_records = width / w;
+            // Create the new records.
+            let mut records = Vec::with_capacity(num_full_width_records+1);
+            for i in 0..num_full_width_records {
+                records.push(BEDGraphRecord {
+                    seqname: self.seqname.to_string(),
+                    start: self.start + i * w,
+                    end: self.start + (i + 1) * w,
+                    score: self.score,
+                });
+            }
+            // Handle the last record if it's the final one and not full-width
+            if is_final_record && !width.is_multiple_of(w) {
+                records.push(BEDGraphRecord {
+                    seqname: self.seqname.to_string(),
+                    start: self.start + num_full_width_records * w,
+                    end: self.end,
+                    score: self.score,
+                });
+            }
+            Ok(records)
+        } else {
+            // Return an error if the width is not divisible by 'w'.
+            Err(BEDGraphRecordNotDivisible{
+                start: self.start,
+                end: self.end,
+                width: w,
+            })
+        }
+    }
+}
+
+/// Implement `Display` for `BEDRecord`. Records with any of the optional columns
+/// set are written in six-column format, using "." for missing values.
+impl fmt::Display for BEDRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.name.is_none() && self.score.is_none() && self.strand.is_none() {
+            writeln!(f, "{}\t{}\t{}", self.seqname, self.start, self.end)
+        } else {
+            let score = match self.score {
+                Some(score) => score.to_string(),
+                None => String::from("."),
+            };
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                self.seqname,
+                self.start,
+                self.end,
+                self.name.as_deref().unwrap_or("."),
+                score,
+                self.strand(),
+            )
+        }
+    }
+}
+
+/// Implement `Display` for `BEDGraphRecord`.
+impl fmt::Display for BEDGraphRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}\t{}\t{}\t{}", self.seqname, self.start, self.end, self.score)
+    }
+}
+
+/// name, length, and topology of a single contig
+#[derive(Debug, PartialEq, Clone)]
+pub struct Contig {
+    name: String,
+    length: usize,
+    circular: bool,
+}
+
+impl Contig {
+    pub fn new(name: String, length: usize, circular: bool) -> Contig {
+        Contig { name, length, circular }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_circular(&self) -> bool {
+        self.circular
+    }
+}
+
+/// line of a genome file: contig name, length, and an optional topology
+/// column containing either "circular" or "linear"
+#[derive(Debug, Deserialize)]
+struct GenomeFileRecord {
+    name: String,
+    length: usize,
+    #[serde(default)]
+    topology: Option<String>,
+}
+
+/// holds the contigs of a genome, in the order they were given
+pub struct Genome {
+    contigs: Vec<Contig>,
+}
+
+impl Genome {
+
+    pub fn from_contig_vec(contigs: Vec<Contig>) -> Genome {
+        Genome { contigs }
+    }
+
+    /// Read a genome file, such as a chrom.sizes file. Contigs are linear unless
+    /// the optional third column reads "circular".
+    pub fn from_file(fname: &path::PathBuf) -> Result<Genome, Box<dyn Error>> {
+
+        let file = File::open(fname).unwrap_or_else(|err| {
+            eprintln!("Problem reading genome file {:?}: {}", fname, err);
+            process::exit(1);
+        });
+        let buf_reader = BufReader::new(file);
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(buf_reader);
+        let mut contigs: Vec<Contig> = Vec::new();
+
+        for result in rdr.deserialize() {
+            let record: GenomeFileRecord = result.unwrap_or_else(|err| {
+                eprintln!("Problem with your genome records. Is {:?} a properly-formed genome file?: {}", fname, err);
+                process::exit(1);
+            });
+            let circular = record.topology.as_deref() == Some("circular");
+            contigs.push(Contig::new(record.name, record.length, circular));
+        }
+        Ok(Genome{ contigs })
+    }
+
+    /// Returns number of contigs in self
+    pub fn len(&self) -> usize {
+        self.contigs.len()
+    }
+
+    /// Returns true if self contains no contigs
+    pub fn is_empty(&self) -> bool {
+        self.contigs.is_empty()
+    }
+
+    /// Iterate over the contigs in self
+    pub fn iter(&self) -> std::slice::Iter<'_, Contig> {
+        self.contigs.iter()
+    }
+
+    /// returns the contig named seqname, or an error if it is absent
+    pub fn get_contig(&self, seqname: &str) -> Result<&Contig, Box<dyn Error>> {
+        self.contigs.iter()
+            .find(|x| x.name == seqname)
+            .ok_or_else(|| {
+                Box::new(ContigNotFound { seqname: seqname.to_string() }) as Box<dyn Error>
+            })
+    }
+
+    /// returns the length of the contig named seqname
+    pub fn get_length(&self, seqname: &str) -> Result<usize, Box<dyn Error>> {
+        Ok(self.get_contig(seqname)?.length)
+    }
+
+    /// returns whether the contig named seqname is circular
+    pub fn is_circular(&self, seqname: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self.get_contig(seqname)?.circular)
+    }
+}
+
+/// holds a bed file
+pub struct BEDData {
+    data: Vec<BEDRecord>,
+}
+
+impl BEDData {
+
+    pub fn from_record_vec(rec_vec: Vec<BEDRecord>) -> BEDData {
+        BEDData{ data: rec_vec }
+    }
+
+    /// Read a bed file with three to six columns
+    pub fn from_file(fname: &path::PathBuf) -> Result<BEDData, Box<dyn Error>> {
+
+        let file = File::open(fname).unwrap_or_else(|err| {
+            eprintln!("Problem reading bed file {:?}: {}", fname, err);
+            process::exit(1);
+        });
+        // open buffered reader to bed file
+        let buf_reader = BufReader::new(file);
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(buf_reader);
+        let mut records: Vec<BEDRecord> = Vec::new();
+
+        for result in rdr.deserialize() {
+            let record = result.unwrap_or_else(|err| {
+                eprintln!("Problem with your bed records. Is {:?} a properly-formed bed file?: {}", fname, err);
+                process::exit(1);
+            });
+            records.push(record);
+        }
+        Ok(BEDData{ data: records })
+    }
+
+    /// Writes the bed data to stdout
+    pub fn print(&self) -> Result<(), Box<dyn Error>> {
+        let mut stdout = stdout();
+        for record in &self.data {
+            write!(stdout, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the bed data to a bed file
+    pub fn to_file(&self, fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+        let outf = File::create(fname)?;
+        let mut writer = BufWriter::new(outf);
+
+        for record in &self.data {
+            write!(writer, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Returns number of records in self
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if self contains no records
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterate over the records in self
+    pub fn iter(&self) -> std::slice::Iter<'_, BEDRecord> {
+        self.data.iter()
+    }
+
+    /// extends each record by left bp upstream and right bp downstream.
+    /// If stranded is true, left and right are swapped for reverse-strand records,
+    /// so that left always refers to the 5' side.
+    /// Records are clipped to linear contig ends and wrapped around circular contigs.
+    pub fn slop(
+            &self,
+            genome: &Genome,
+            left: usize,
+            right: usize,
+            stranded: bool,
+    ) -> Result<BEDData, Box<dyn Error>> {
+        let mut records: Vec<BEDRecord> = Vec::with_capacity(self.len());
+        for record in self.iter() {
+            let (up, down) = if stranded && record.is_reverse() {
+                (right as i64, left as i64)
+            } else {
+                (left as i64, right as i64)
+            };
+            let mut placed = record.place(
+                record.start as i64 - up,
+                record.end as i64 + down,
+                genome,
+            )?;
+            records.append(&mut placed);
+        }
+        Ok(BEDData{data: records})
+    }
+
+    /// returns the flanking regions of each record, left bp upstream of
+    /// the start and right bp downstream of the end. Either may be zero to omit that flank.
+    /// If stranded is true, left and right are swapped for reverse-strand records,
+    /// so that, for example, left flanks of transcription start sites are promoters.
+    /// Flanks are clipped to linear contig ends and wrapped around circular contigs.
+    pub fn flank(
+            &self,
+            genome: &Genome,
+            left: usize,
+            right: usize,
+            stranded: bool,
+    ) -> Result<BEDData, Box<dyn Error>> {
+        let mut records: Vec<BEDRecord> = Vec::with_capacity(self.len() * 2);
+        for record in self.iter() {
+            let (up, down) = if stranded && record.is_reverse() {
+                (right as i64, left as i64)
+            } else {
+                (left as i64, right as i64)
+            };
+            let start = record.start as i64;
+            let end = record.end as i64;
+            if up > 0 {
+                records.append(&mut record.place(start - up, start, genome)?);
+            }
+            if down > 0 {
+                records.append(&mut record.place(end, end + down, genome)?);
+            }
+        }
+        Ok(BEDData{data: records})
+    }
+
+    /// sets each record to the given width, keeping the anchor position fixed.
+    /// [Anchor::FivePrime] and [Anchor::ThreePrime] respect each record's strand, with
+    /// unstranded records treated as forward-strand.
+    /// Records are clipped to linear contig ends and wrapped around circular contigs.
+    /// Returns an error if width is zero.
+    pub fn resize(
+            &self,
+            genome: &Genome,
+            width: usize,
+            anchor: Anchor,
+    ) -> Result<BEDData, Box<dyn Error>> {
+        if width == 0 {
+            return Err(Box::new(InvalidParameter {
+                message: String::from("records cannot be resized to a width of zero"),
+            }));
+        }
+        let width = width as i64;
+        let mut records: Vec<BEDRecord> = Vec::with_capacity(self.len());
+        for record in self.iter() {
+            let start = record.start as i64;
+            let end = record.end as i64;
+            let (new_start, new_end) = match (anchor, record.is_reverse()) {
+                (Anchor::FivePrime, false) | (Anchor::ThreePrime, true) => (start, start + width),
+                (Anchor::FivePrime, true) | (Anchor::ThreePrime, false) => (end - width, end),
+                (Anchor::Center, _) => {
+                    let new_start = (start + end) / 2 - width / 2;
+                    (new_start, new_start + width)
+                },
+            };
+            records.append(&mut record.place(new_start, new_end, genome)?);
+        }
+        Ok(BEDData{data: records})
+    }
+
+    /// returns the contig index and width of each record within genome, and an error
+    /// if a record lies on a contig not in genome
+    fn get_contig_widths(&self, genome: &Genome) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+        let contig_idx: HashMap<&str, usize> = genome.iter()
+            .enumerate()
+            .map(|(i,x)| (x.name.as_str(), i))
+            .collect();
+        self.iter().map(|x| {
+            match contig_idx.get(x.seqname.as_str()) {
+                Some(i) => Ok((*i, x.end - x.start)),
+                None => Err(Box::new(ContigNotFound { seqname: x.seqname.to_string() }) as Box<dyn Error>),
+            }
+        }).collect()
+    }
+
+    /// returns a copy of self with each record moved to a random position in genome,
+    /// keeping its width, name, score, and strand. Positions are drawn uniformly from all
+    /// possible start positions in the genome, or on the record's own contig if
+    /// within_contig is true, avoiding the exclude regions. Records placed across the
+    /// origin of a circular contig are split in two. The same seed gives the same result.
+    pub fn shuffle(
+            &self,
+            genome: &Genome,
+            exclude: Option<&BEDData>,
+            within_contig: bool,
+            seed: u64,
+    ) -> Result<BEDData, Box<dyn Error>> {
+        let shuffler = Shuffler::new(genome, exclude, within_contig);
+        let mut rng = SplitMix64::new(seed);
+        let mut records: Vec<BEDRecord> = Vec::with_capacity(self.len());
+        for ((home, width), record) in self.get_contig_widths(genome)?.iter().zip(self.iter()) {
+            let pieces = shuffler.place(*home, *width, &mut rng).ok_or_else(|| {
+                InvalidParameter {
+                    message: format!("could not place {} without overlapping excluded regions", record),
+                }
+            })?;
+            for (contig, start, end) in pieces {
+                let mut shuffled = record.clone();
+                shuffled.seqname = genome.contigs[contig].name.to_string();
+                shuffled.set_start(&start);
+                shuffled.set_end(&end);
+                records.push(shuffled);
+            }
+        }
+        Ok(BEDData{data: records})
+    }
+
+    /// tests whether the records in self overlap features more than expected by chance.
+    /// The observed overlap is compared with a null distribution from overlaps of
+    /// repeatedly shuffled copies of self, as in [BEDData::shuffle], with features
+    /// held in place. Permutation k is shuffled with a generator seeded from params.seed
+    /// and k, so results are reproducible and independent of params.n_threads.
+    pub fn enrichment(
+            &self,
+            features: &BEDData,
+            genome: &Genome,
+            params: &EnrichmentParams,
+    ) -> Result<Enrichment, Box<dyn Error>> {
+        if params.n_permutations < 2 {
+            return Err(Box::new(InvalidParameter {
+                message: String::from("the permutation test needs at least 2 permutations"),
+            }));
+        }
+        let contig_widths = self.get_contig_widths(genome)?;
+        let merged = features.merged_intervals();
+        let feature_intervals: Vec<Vec<(usize, usize)>> = genome.iter()
+            .map(|x| merged.get(&x.name).cloned().unwrap_or_default())
+            .collect();
+        let measure = |pieces: &[(usize, usize, usize)]| -> f64 {
+            let bp: usize = pieces.iter()
+                .map(|(contig, start, end)| overlap_bp_merged(&feature_intervals[*contig], *start, *end))
+                .sum();
+            match params.measure {
+                OverlapMeasure::Count => if bp > 0 { 1.0 } else { 0.0 },
+                OverlapMeasure::Bp => bp as f64,
+            }
+        };
+        let observed: f64 = self.iter()
+            .zip(contig_widths.iter())
+            .map(|(x, (contig, _))| measure(&[(*contig, x.start, x.end)]))
+            .sum();
+
+        let shuffler = Shuffler::new(genome, params.exclude, params.within_contig);
+        let permute = |k: usize| -> Option<f64> {
+            let mut rng = SplitMix64::new(
+                params.seed ^ (k as u64).wrapping_mul(0xD1B5_4A32_D192_ED03),
+            );
+            let mut total = 0.0;
+            for (home, width) in &contig_widths {
+                total += measure(&shuffler.place(*home, *width, &mut rng)?);
+            }
+            Some(total)
+        };
+        let permutations: Vec<usize> = (0..params.n_permutations).collect();
+        let chunk_size = params.n_permutations.div_ceil(params.n_threads.max(1));
+        let null: Option<Vec<f64>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = permutations.chunks(chunk_size)
+                .map(|chunk| scope.spawn(|| chunk.iter().map(|k| permute(*k)).collect::<Vec<_>>()))
+                .collect();
+            handles.into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        match null {
+            Some(null) => Ok(Enrichment{observed, null}),
+            None => Err(Box::new(InvalidParameter {
+                message: String::from("could not place every record without overlapping excluded regions"),
+            })),
+        }
+    }
+
+    /// Jaccard index between the base pairs covered by self and other: the bp covered
+    /// by both divided by the bp covered by either, or NaN if neither covers any
+    pub fn jaccard(&self, other: &BEDData) -> f64 {
+        merged_jaccard(&self.merged_intervals(), &other.merged_intervals())
+    }
+
+    /// Fisher's exact test of whether the intervals of self and other overlap more or
+    /// less than expected, in the manner of `bedtools fisher`. After merging each set,
+    /// the contingency table counts intervals of self overlapping other, intervals of
+    /// self not overlapping other, and intervals of other not overlapping self. Intervals
+    /// in neither set are estimated as the bp of genome covered by neither, divided by
+    /// the mean width of the merged intervals of both sets. Returns an error if either
+    /// set has records on a contig missing from genome.
+    pub fn fisher(
+            &self,
+            other: &BEDData,
+            genome: &Genome,
+    ) -> Result<FisherResult, Box<dyn Error>> {
+        let a = self.merged_intervals();
+        let b = other.merged_intervals();
+        for contig in a.keys().chain(b.keys()) {
+            genome.get_contig(contig)?;
+        }
+        // number of intervals in x overlapping y, number not overlapping, and bp of x
+        let count = |x: &HashMap<String, Vec<(usize, usize)>>, y: &HashMap<String, Vec<(usize, usize)>>| {
+            let (mut hits, mut misses, mut bp) = (0, 0, 0);
+            for (contig, intervals) in x {
+                for (start, end) in intervals {
+                    if y.get(contig).is_some_and(|z| overlaps_merged(z, *start, *end)) {
+                        hits += 1;
+                    } else {
+                        misses += 1;
+                    }
+                    bp += end - start;
+                }
+            }
+            (hits, misses, bp)
+        };
+        let (n11, n12, bp_a) = count(&a, &b);
+        let (m11, n21, bp_b) = count(&b, &a);
+        let n_intervals = n11 + n12 + m11 + n21;
+        if n_intervals == 0 {
+            return Err(Box::new(InvalidParameter {
+                message: String::from("Fisher's test needs at least one interval"),
+            }));
+        }
+        let intersection: usize = a.iter()
+            .filter_map(|(contig, intervals)| b.get(contig).map(|x| intersection_bp(intervals, x)))
+            .sum();
+        let genome_size: usize = genome.iter().map(|x| x.length).sum();
+        let mean_width = (bp_a + bp_b) as f64 / n_intervals as f64;
+        let uncovered = genome_size.saturating_sub(bp_a + bp_b - intersection);
+        let n22 = (uncovered as f64 / mean_width).round() as u64;
+        Ok(FisherResult::from_table([[n11, n12], [n21, n22]]))
+    }
+
+    /// labels each record in self with the gene whose transcription start site is
+    /// nearest its midpoint, the distance to that site, and the genomic context of the
+    /// midpoint given the gene models in annotation. Positions in a gene are assigned
+    /// to its CDS, UTRs, or introns, positions near a start site to its promoter as
+    /// set in params, and all others are intergenic. Bacterial genes may be grouped into
+    /// operons with params.operon_gap.
+    pub fn annotate_regions(
+            &self,
+            annotation: &GffData,
+            params: &AnnotationParams,
+    ) -> AnnotationData {
+        let regions: Vec<(BEDRecord, usize)> = self.iter()
+            .map(|x| (x.clone(), (x.start + x.end) / 2))
+            .collect();
+        annotate_positions(regions, &annotation.gene_models(), params)
+    }
+
+    /// returns the intervals of each contig, sorted and with overlapping
+    /// and book-ended intervals merged
+    pub fn merged_intervals(&self) -> HashMap<String, Vec<(usize, usize)>> {
+        let mut by_contig: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        for record in self.iter() {
+            by_contig.entry(record.seqname.to_string())
+                .or_default()
+                .push((record.start, record.end));
+        }
+        for intervals in by_contig.values_mut() {
+            intervals.sort();
+            let mut merged: Vec<(usize, usize)> = Vec::with_capacity(intervals.len());
+            for (start, end) in intervals.iter() {
+                match merged.last_mut() {
+                    Some(last) if *start <= last.1 => last.1 = last.1.max(*end),
+                    _ => merged.push((*start, *end)),
+                }
+            }
+            *intervals = merged;
+        }
+        by_contig
+    }
+}
+
+/// true if [start, end) overlaps any of the sorted, merged intervals,
+/// as returned by [BEDData::merged_intervals]
+fn overlaps_merged(intervals: &[(usize, usize)], start: usize, end: usize) -> bool {
+    let i = intervals.partition_point(|x| x.1 <= start);
+    i < intervals.len() && intervals[i].0 < end
+}
+
+/// number of base pairs by which [start, end) overlaps the sorted, merged intervals
+fn overlap_bp_merged(intervals: &[(usize, usize)], start: usize, end: usize) -> usize {
+    let first = intervals.partition_point(|x| x.1 <= start);
+    intervals[first..].iter()
+        .take_while(|x| x.0 < end)
+        .map(|x| x.1.min(end) - x.0.max(start))
+        .sum()
+}
+
+/// SplitMix64 pseudorandom number generator, used for seeded, reproducible shuffling
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// a number in [0, n), for n > 0
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// places intervals at random positions in a genome, avoiding excluded regions
+struct Shuffler {
+    lengths: Vec<usize>,
+    circular: Vec<bool>,
+    exclude: Vec<Vec<(usize, usize)>>,
+    within_contig: bool,
+}
+
+impl Shuffler {
+    /// attempts to place an interval this many times before giving up
+    const MAX_ATTEMPTS: usize = 1_000;
+
+    fn new(genome: &Genome, exclude: Option<&BEDData>, within_contig: bool) -> Shuffler {
+        let merged = exclude.map(|x| x.merged_intervals()).unwrap_or_default();
+        Shuffler {
+            lengths: genome.iter().map(|x| x.length).collect(),
+            circular: genome.iter().map(|x| x.circular).collect(),
+            exclude: genome.iter()
+                .map(|x| merged.get(&x.name).cloned().unwrap_or_default())
+                .collect(),
+            within_contig,
+        }
+    }
+
+    /// number of positions at which an interval of width bp can start on a contig
+    fn n_starts(&self, contig: usize, width: usize) -> usize {
+        if self.circular[contig] {
+            if width <= self.lengths[contig] { self.lengths[contig] } else { 0 }
+        } else {
+            (self.lengths[contig] + 1).saturating_sub(width)
+        }
+    }
+
+    /// places an interval of width bp, from the contig at index home, uniformly over the
+    /// allowed start positions, returning its (contig index, start, end) pieces. Intervals
+    /// spanning the origin of a circular contig are split in two. Returns None if every
+    /// attempt overlapped an excluded region or no contig can hold the interval.
+    fn place(
+            &self,
+            home: usize,
+            width: usize,
+            rng: &mut SplitMix64,
+    ) -> Option<Vec<(usize, usize, usize)>> {
+        let contigs: Vec<usize> = if self.within_contig {
+            vec![home]
+        } else {
+            (0..self.lengths.len()).collect()
+        };
+        let cumulative: Vec<usize> = contigs.iter()
+            .scan(0, |acc, x| {
+                *acc += self.n_starts(*x, width);
+                Some(*acc)
+            }).collect();
+        let total = *cumulative.last()?;
+        if total == 0 {
+            return None;
+        }
+        for _ in 0..Shuffler::MAX_ATTEMPTS {
+            let draw = rng.below(total as u64) as usize;
+            let i = cumulative.partition_point(|x| *x <= draw);
+            let contig = contigs[i];
+            let start = draw - if i == 0 { 0 } else { cumulative[i - 1] };
+            let end = start + width;
+            let length = self.lengths[contig];
+            let pieces = if end > length {
+                vec![(contig, start, length), (contig, 0, end - length)]
+            } else {
+                vec![(contig, start, end)]
+            };
+            if !pieces.iter().any(|x| overlaps_merged(&self.exclude[contig], x.1, x.2)) {
+                return Some(pieces);
+            }
+        }
+        None
+    }
+}
+
+/// measure of the overlap between two sets of intervals, as used by [BEDData::enrichment]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OverlapMeasure {
+    /// number of query intervals overlapping any feature by at least one bp
+    Count,
+    /// total bp of the query intervals overlapping features
+    Bp,
+}
+
+/// parameters of the permutation test, [BEDData::enrichment]
+#[derive(Clone)]
+pub struct EnrichmentParams<'a> {
+    pub measure: OverlapMeasure,
+    pub n_permutations: usize,
+    /// seed of the random number generator, so that results are reproducible
+    pub seed: u64,
+    /// if given, shuffled intervals are never placed overlapping these regions
+    pub exclude: Option<&'a BEDData>,
+    /// if true, shuffled intervals stay on their own contig
+    pub within_contig: bool,
+    /// number of threads over which permutations are divided. Results do not
+    /// depend on the number of threads.
+    pub n_threads: usize,
+}
+
+impl Default for EnrichmentParams<'_> {
+    fn default() -> Self {
+        EnrichmentParams {
+            measure: OverlapMeasure::Count,
+            n_permutations: 1_000,
+            seed: 0,
+            exclude: None,
+            within_contig: false,
+            n_threads: 1,
+        }
+    }
+}
+
+/// result of the permutation test, [BEDData::enrichment]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Enrichment {
+    observed: f64,
+    null: Vec<f64>,
+}
+
+impl Enrichment {
+    /// overlap of the query intervals with the features
+    pub fn observed(&self) -> f64 {
+        self.observed
+    }
+
+    /// overlap of each set of shuffled query intervals with the features
+    pub fn null(&self) -> &[f64] {
+        &self.null
+    }
+
+    /// mean overlap under the null
+    pub fn expected(&self) -> f64 {
+        self.null.iter().sum::<f64>() / self.null.len() as f64
+    }
+
+    /// sample standard deviation of the overlap under the null
+    pub fn null_sd(&self) -> f64 {
+        let expected = self.expected();
+        let ss: f64 = self.null.iter().map(|x| (x - expected).powi(2)).sum();
+        (ss / (self.null.len() as f64 - 1.0)).sqrt()
+    }
+
+    /// observed overlap divided by expected overlap
+    pub fn fold_enrichment(&self) -> f64 {
+        self.observed / self.expected()
+    }
+
+    /// one-sided empirical p-value of enrichment, (1 + b) / (1 + n), where b of the
+    /// n permutations had overlap at least as great as observed
+    pub fn p_value(&self) -> f64 {
+        let b = self.null.iter().filter(|x| **x >= self.observed).count();
+        (1 + b) as f64 / (1 + self.null.len()) as f64
+    }
+
+    /// (observed - expected) / null_sd, or NaN if the null has no variance
+    pub fn z_score(&self) -> f64 {
+        let sd = self.null_sd();
+        if sd == 0.0 { f64::NAN } else { (self.observed - self.expected()) / sd }
+    }
+}
+
+/// bp of overlap between two sorted lists of merged intervals
+fn intersection_bp(a: &[(usize, usize)], b: &[(usize, usize)]) -> usize {
+    let (mut i, mut j, mut bp) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start < end {
+            bp += end - start;
+        }
+        if a[i].1 < b[j].1 { i += 1 } else { j += 1 }
+    }
+    bp
+}
+
+/// natural log of the hypergeometric probability of k successes in a sample of
+/// size n drawn from a population of size total containing k_total successes
+fn ln_hypergeometric(k: u64, k_total: u64, n: u64, total: u64) -> f64 {
+    let ln_choose = |n: u64, k: u64| -> f64 {
+        ln_gamma(n as f64 + 1.0) - ln_gamma(k as f64 + 1.0) - ln_gamma((n - k) as f64 + 1.0)
+    };
+    ln_choose(k_total, k) + ln_choose(total - k_total, n - k) - ln_choose(total, n)
+}
+
+/// result of Fisher's exact test on the overlap of two interval sets,
+/// as returned by [BEDData::fisher]
+#[derive(Debug, PartialEq, Clone)]
+pub struct FisherResult {
+    table: [[u64; 2]; 2],
+    p_left: f64,
+    p_right: f64,
+    p_two_tailed: f64,
+}
+
+impl FisherResult {
+    /// runs Fisher's exact test on the 2x2 contingency table
+    fn from_table(table: [[u64; 2]; 2]) -> FisherResult {
+        let [[n11, n12], [n21, n22]] = table;
+        let row1 = n11 + n12;
+        let col1 = n11 + n21;
+        let total = n11 + n12 + n21 + n22;
+        let lo = col1.saturating_sub(n21 + n22);
+        let hi = row1.min(col1);
+        let probs: Vec<f64> = (lo..=hi)
+            .map(|k| ln_hypergeometric(k, row1, col1, total).exp())
+            .collect();
+        let observed = probs[(n11 - lo) as usize];
+        let p_left: f64 = probs[..=(n11 - lo) as usize].iter().sum();
+        let p_right: f64 = probs[(n11 - lo) as usize..].iter().sum();
+        // tables as or less probable than that observed, with tolerance for rounding
+        let p_two_tailed: f64 = probs.iter()
+            .filter(|x| **x <= observed * (1.0 + 1e-7))
+            .sum();
+        FisherResult {
+            table,
+            p_left: p_left.min(1.0),
+            p_right: p_right.min(1.0),
+            p_two_tailed: p_two_tailed.min(1.0),
+        }
+    }
+
+    /// the contingency table, [[both, only first], [only second, neither]]
+    pub fn table(&self) -> [[u64; 2]; 2] {
+        self.table
+    }
+
+    /// sample odds ratio, (both * neither) / (only first * only second)
+    pub fn odds_ratio(&self) -> f64 {
+        let [[n11, n12], [n21, n22]] = self.table;
+        (n11 * n22) as f64 / (n12 * n21) as f64
+    }
+
+    /// p-value of depletion of overlaps
+    pub fn p_left(&self) -> f64 {
+        self.p_left
+    }
+
+    /// p-value of enrichment of overlaps
+    pub fn p_right(&self) -> f64 {
+        self.p_right
+    }
+
+    pub fn p_two_tailed(&self) -> f64 {
+        self.p_two_tailed
+    }
+}
+
+/// returns the matrix of pairwise Jaccard indices between interval sets, with rows
+/// labelled by names. See [BEDData::jaccard]. Like the off-diagonal entries, the
+/// diagonal is computed from the intervals, so it is 1, or NaN for an empty set.
+pub fn jaccard_matrix(sets: &[&BEDData], names: &[&str]) -> Result<SignalMatrix, Box<dyn Error>> {
+    if sets.len() != names.len() {
+        return Err(Box::new(InvalidParameter {
+            message: format!(
+                "{} sample names were given for {} interval sets",
+                names.len(),
+                sets.len(),
+            ),
+        }));
+    }
+    let merged: Vec<HashMap<String, Vec<(usize, usize)>>> = sets.iter()
+        .map(|x| x.merged_intervals())
+        .collect();
+    let n = sets.len();
+    let mut values = vec![f64::NAN; n * n];
+    for i in 0..n {
+        for j in i..n {
+            let jaccard = merged_jaccard(&merged[i], &merged[j]);
+            values[i * n + j] = jaccard;
+            values[j * n + i] = jaccard;
+        }
+    }
+    Ok(SignalMatrix {
+        row_labels: names.iter().map(|x| x.to_string()).collect(),
+        values,
+        ncol: n,
+    })
+}
+
+/// bp-level Jaccard index between two sets of merged intervals, or NaN if both are empty
+fn merged_jaccard(
+        a: &HashMap<String, Vec<(usize, usize)>>,
+        b: &HashMap<String, Vec<(usize, usize)>>,
+) -> f64 {
+    let bp = |x: &HashMap<String, Vec<(usize, usize)>>| -> usize {
+        x.values().flatten().map(|(start, end)| end - start).sum()
+    };
+    let intersection: usize = a.iter()
+        .filter_map(|(contig, intervals)| b.get(contig).map(|x| intersection_bp(intervals, x)))
+        .sum();
+    let union = bp(a) + bp(b) - intersection;
+    intersection as f64 / union as f64
+}
+
+/// holds a bedgraph file
+pub struct BEDGraphData {
+    data: Vec<BEDGraphRecord>,
+}
+
+impl BEDGraphData {
+
+    pub fn from_record_vec(rec_vec: Vec<BEDGraphRecord>) -> BEDGraphData {
+        BEDGraphData{ data: rec_vec }
+    }
+
+    /// Parse bedgraph records from stdin
+    pub fn from_stdin() -> Result<BEDGraphData, Box<dyn Error>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_reader(stdin());
+        let mut records: Vec<BEDGraphRecord> = Vec::new();
+
+        for result in rdr.deserialize() {
+            let record = result.unwrap_or_else(|err| {
+                eprintln!("Problem with your bedgraph records. Is the stdin a properly-formed bedgraph file?: {}", err);
+                process::exit(1);
+            });
+            records.push(record);
+        }
+        Ok(BEDGraphData{ data: records })
+    }
+
+    /// Parse bedgraph records from stdin, printing contiguous regions as bed format
+    pub fn print_contiguous_regions_from_stdin() -> Result<(), Box<dyn Error>> {
+        // read from stdin
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_reader(stdin());
+        // establish connection to stdout
+        let mut stdout = stdout();
+
+        let mut region = BEDRecord::new("".into(), 0, 0);
+        let mut first_row = true;
+
+        for result in rdr.deserialize() {
+            let record: BEDGraphRecord = result.unwrap_or_else(|err| {
+                eprintln!("Problem with your bedgraph records. Is the stdin a properly-formed bedgraph file?: {}", err);
+                process::exit(1);
+            });
+            if region.seqname != record.seqname {
+                // if we've hit a new seqname and this is not the first row, then print bed record
+                // prior to setting new region
+                if !first_row {
+                    write!(stdout, "{}", region)?;
+                }
+                region.set(&record.seqname, &record.start, &record.end);
+            } else {
+                // if we're in the same seqname, check whether region's end and record's start are
+                // equal. If they are equal, region is contiguous with record, so set region end to
+                // record end
+                if region.end == record.start {
+                    region.set_end(&record.end);
+                // if they're not equal, these are not contiguous, so print the region and re-set
+                // region to have this record's attributes
+                } else {
+                    write!(stdout, "{}", region)?;
+                    region.set_start(&record.start);
+                    region.set_end(&record.end);
+                }
+            }
+            first_row = false;
+        }
+        // write the final region if it is different than beginning region
+        if !region.seqname.is_empty() {
+            write!(stdout, "{}", region)?;
+        }
+        Ok(())
+    }
+
+    /// Read begraph file line-by-line, printing each contigous regions to stdout as they are
+    /// identified
+    pub fn print_contiguous_regions(fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+
+        let file = File::open(fname).unwrap_or_else(|err| {
+            eprintln!("Problem reading bedgraph file {:?}: {}", fname, err);
+            process::exit(1);
+        });
+        // open buffered reader to bedgraph file
+        let buf_reader = BufReader::new(file);
+        // establish connection to stdout
+        let mut stdout = stdout();
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_reader(buf_reader);
+
+        let mut region = BEDRecord::new("".into(), 0, 0);
+        let mut first_row = true;
+
+        for result in rdr.deserialize() {
+            let record: BEDGraphRecord = result.unwrap_or_else(|err| {
+                eprintln!("Problem with your bedgraph records. Is {:?} a properly-formed bedgraph file?: {}", fname, err);
+                process::exit(1);
+            });
+            if region.seqname != record.seqname {
+                // if we've hit a new seqname and this is not the first row, then print bed record
+                // prior to setting new region
+                if !first_row {
+                    write!(stdout, "{}", region)?;
+                }
+                region.set(&record.seqname, &record.start, &record.end);
+            } else {
+                // if we're in the same seqname, check whether region's end and record's start are
+                // equal. If they are equal, region is contiguous with record, so set region end to
+                // record end
+                if region.end == record.start {
+                    region.set_end(&record.end);
+                // if they're not equal, these are not contiguous, so print the region and re-set
+                // region to have this record's attributes
+                } else {
+                    write!(stdout, "{}", region)?;
+                    region.set_start(&record.start);
+                    region.set_end(&record.end);
+                }
+            }
+            first_row = false;
+        }
+        // write the final region if it is different than beginning region
+        if !region.seqname.is_empty() {
+            write!(stdout, "{}", region)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a bedgraph file
+    pub fn from_file(fname: &path::PathBuf) -> Result<BEDGraphData, Box<dyn Error>> {
+        BEDGraphData::from_file_with_missing(fname, &MISSING_TOKENS)
+    }
+
+    /// Read a bedgraph file, reading scores matching any of missing_tokens, in
+    /// addition to [MISSING_TOKENS], as missing values represented by NaN
+    pub fn from_file_with_missing(
+            fname: &path::PathBuf,
+            missing_tokens: &[&str],
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+
+        let file = File::open(fname).unwrap_or_else(|err| {
+            eprintln!("Problem reading bedgraph file {:?}: {}", fname, err);
+            process::exit(1);
+        });
+        // open buffered reader to bedgraph file
+        let buf_reader = BufReader::new(file);
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_reader(buf_reader);
+        let mut records: Vec<BEDGraphRecord> = Vec::new();
+
+        for result in rdr.records() {
+            let record = result.and_then(|mut row| {
+                if row.get(3).is_some_and(|x| missing_tokens.contains(&x)) {
+                    row = row.iter()
+                        .enumerate()
+                        .map(|(i,x)| if i == 3 { "NaN" } else { x })
+                        .collect();
+                }
+                row.deserialize::<BEDGraphRecord>(None)
+            }).unwrap_or_else(|err| {
+                eprintln!("Problem with your bedgraph records. Is {:?} a properly-formed bedgraph file?: {}", fname, err);
+                process::exit(1);
+            });
+            records.push(record);
+        }
+        Ok(BEDGraphData{ data: records })
+    }
+
+    /// Writes the bedgraph data to stdout
+    pub fn print(&self) -> Result<(), Box<dyn Error>> {
+        let mut stdout = stdout();
+        for record in &self.data {
+            write!(stdout, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the bedgraph data to a bedgraph file
+    pub fn to_file(&self, fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+        let outf = File::create(fname)?;
+        let mut writer = BufWriter::new(outf);
+
+        for record in &self.data {
+            writeln!(writer, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Returns number of records in self
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if self contains no records
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterate over each record in the database as a [StrandedSequence] value pair
+    fn iter(&self) -> BEDGraphDataIter<'_> {
+        BEDGraphDataIter { loc: 0, bgd: self, size: self.len() }
+    }
+    
+    /// filters records in self to those within the given range
+    /// returns a new BEDGraphData instance
+    pub fn filter(
+            &self,
+            seqname: &str,
+            start: &usize,
+            end: &usize,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+
+        let records: Vec::<BEDGraphRecord> = self.iter()
+            .filter(|x| {
+                x.seqname == seqname
+                && x.start >= *start
+                && x.end <= *end
+            }).collect();
+        
+        Ok(BEDGraphData{data: records})
+    }
+
+    /// returns scores in counts per million, dividing each score by the sum of all scores.
+    /// Missing scores remain missing, and are left out of the sum if skipped by nan_policy,
+    /// or otherwise make every score missing. The same holds for the other normalizations.
+    pub fn get_cpm(&self, nan_policy: NanPolicy) -> Result<Vec<f64>, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        let sum = nan_policy.sum(scores.iter().copied());
+        let cpm: Vec<f64> = scores.iter()
+            .map(|a| a / sum * 1_000_000.0)
+            .collect();
+        Ok(cpm)
+    }
+
+    pub fn to_cpm(&mut self, nan_policy: NanPolicy) -> Result<(), Box<dyn Error>> {
+        let cpm = self.get_cpm(nan_policy)?;
+        self.set_scores(&cpm);
+        Ok(())
+    }
+
+    /// returns scores in reads per kilobase per million (RPKM), dividing each score
+    /// by the sum of all scores in millions and by its record's width in kb.
+    /// For fragment counts from paired-end data this is FPKM.
+    pub fn get_rpkm(&self, nan_policy: NanPolicy) -> Result<Vec<f64>, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        let sum = nan_policy.sum(scores.iter().copied());
+        let rpkm: Vec<f64> = self.iter()
+            .map(|x| x.score / (sum / 1_000_000.0) / (x.width() as f64 / 1_000.0))
+            .collect();
+        Ok(rpkm)
+    }
+
+    pub fn to_rpkm(&mut self, nan_policy: NanPolicy) -> Result<(), Box<dyn Error>> {
+        let rpkm = self.get_rpkm(nan_policy)?;
+        self.set_scores(&rpkm);
+        Ok(())
+    }
+
+    /// returns scores in bins per million (BPM), as in deepTools: each score is divided
+    /// by its record's width in kb, then the results are scaled to sum to one million.
+    /// Applied to records holding per-feature counts, this is TPM.
+    pub fn get_bpm(&self, nan_policy: NanPolicy) -> Result<Vec<f64>, Box<dyn Error>> {
+        let rpk: Vec<f64> = self.iter()
+            .map(|x| x.score / (x.width() as f64 / 1_000.0))
+            .collect();
+        let sum = nan_policy.sum(rpk.iter().copied());
+        let bpm: Vec<f64> = rpk.iter()
+            .map(|a| a / sum * 1_000_000.0)
+            .collect();
+        Ok(bpm)
+    }
+
+    pub fn to_bpm(&mut self, nan_policy: NanPolicy) -> Result<(), Box<dyn Error>> {
+        let bpm = self.get_bpm(nan_policy)?;
+        self.set_scores(&bpm);
+        Ok(())
+    }
+
+    /// returns scores in reads per genomic content (RPGC), scaling per-base coverage
+    /// so that the mean coverage over effective_genome_size bp is 1x
+    pub fn get_rpgc(
+            &self,
+            effective_genome_size: usize,
+            nan_policy: NanPolicy,
+    ) -> Result<Vec<f64>, Box<dyn Error>> {
+        let total = nan_policy.sum(self.iter().map(|x| x.score * x.width() as f64));
+        let factor = effective_genome_size as f64 / total;
+        self.get_scaled(factor)
+    }
+
+    pub fn to_rpgc(&mut self, effective_genome_size: usize, nan_policy: NanPolicy) -> Result<(), Box<dyn Error>> {
+        let rpgc = self.get_rpgc(effective_genome_size, nan_policy)?;
+        self.set_scores(&rpgc);
+        Ok(())
+    }
+
+    /// returns scores multiplied by factor, such as a spike-in scaling factor
+    pub fn get_scaled(&self, factor: f64) -> Result<Vec<f64>, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        Ok(scores.iter().map(|a| a * factor).collect())
+    }
+
+    pub fn to_scaled(&mut self, factor: f64) -> Result<(), Box<dyn Error>> {
+        let scaled = self.get_scaled(factor)?;
+        self.set_scores(&scaled);
+        Ok(())
+    }
+
+    /// returns transcripts per million (TPM) for each region, treating the area
+    /// under the signal in self over a region as that region's count. Missing
+    /// scores are handled by nan_policy as in [BEDGraphData::region_summary], and
+    /// regions whose count is missing are left out of the total if skipped.
+    pub fn get_tpm(&self, regions: &BEDData, nan_policy: NanPolicy) -> Result<Vec<f64>, Box<dyn Error>> {
+        let summaries = self.region_summary(regions, nan_policy)?;
+        let rpk: Vec<f64> = summaries.iter()
+            .map(|x| x.sum / ((x.region.end - x.region.start) as f64 / 1_000.0))
+            .collect();
+        let sum = nan_policy.sum(rpk.iter().copied());
+        Ok(rpk.iter().map(|a| a / sum * 1_000_000.0).collect())
+    }
+
+    /// sets the score column of each region to its TPM. See [BEDGraphData::get_tpm].
+    pub fn to_tpm(&self, regions: &mut BEDData, nan_policy: NanPolicy) -> Result<(), Box<dyn Error>> {
+        let tpm = self.get_tpm(regions, nan_policy)?;
+        for (i,new_score) in tpm.iter().enumerate() {
+            regions.data[i].score = Some(*new_score);
+        }
+        Ok(())
+    }
+
+    fn set_scores(&mut self, scores: &[f64]) {
+        for (i,new_score) in scores.iter().enumerate() {
+            self.data[i].set_score(*new_score);
+        }
+    }
+
+    /// returns a Result, which if successful, contains the score
+    /// column of the bedgraph file as a vector of f64 values
+    pub fn fetch_scores(&self) -> Result<Vec<f64>, Box<dyn Error>> {
+        let scores: Vec::<f64> = self.iter()
+            .map(|record| record.score)
+            .collect();
+        Ok(scores)
+    }
+
+    /// returns a vec of contig names
+    fn get_contigs(&self) -> Vec<String> {
+        let contigs = self.iter()
+            .map(|x| x.seqname)
+            .unique()
+            .collect();
+        contigs
+    }
+
+    fn get_max_end(&self) -> usize {
+        self.iter().map(|x| x.end).max().unwrap()
+    }
+
+    /// returns the greatest end position in self
+    fn get_contig_length(&self, seqname: &str) -> Result<usize, Box<dyn Error>> {
+        let ctg_bg = self.filter(
+            seqname,
+            &0,
+            &usize::MAX,
+        )?;
+        Ok(ctg_bg.get_max_end())
+    }
+
+    /// returns a [Genome] with one linear contig per contig in self, each
+    /// as long as the greatest end position on that contig
+    pub fn get_genome(&self) -> Result<Genome, Box<dyn Error>> {
+        let mut contigs: Vec<Contig> = Vec::new();
+        for contig in self.get_contigs() {
+            let length = self.get_contig_length(&contig)?;
+            contigs.push(Contig::new(contig, length, false));
+        }
+        Ok(Genome{ contigs })
+    }
+
+    /// pads scores with pad_size values at each end. See [BEDGraphData::get_asymmetric_padded_scores].
+    pub fn get_padded_scores(
+            &self,
+            pad_size: usize,
+            circular: bool,
+    ) -> Result<Vec<f64>, Box<dyn Error>> {
+        self.get_asymmetric_padded_scores(pad_size, pad_size, circular)
+    }
+
+    /// pads scores with left_pad values before the first record and right_pad values
+    /// after the last, taken from the opposite end of the contig if circular is true,
+    /// or by repeating the first and last scores if not
+    pub fn get_asymmetric_padded_scores(
+            &self,
+            left_pad: usize,
+            right_pad: usize,
+            circular: bool,
+    ) -> Result<Vec<f64>, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        let n_scores = scores.len();
+        if circular && left_pad.max(right_pad) > n_scores {
+            return Err(Box::new(InvalidParameter {
+                message: format!(
+                    "cannot pad {} records by {} on a circular contig",
+                    n_scores,
+                    left_pad.max(right_pad),
+                ),
+            }));
+        }
+        let mut padded: Vec<f64> = Vec::with_capacity(n_scores + left_pad + right_pad);
+        if circular {
+            padded.extend_from_slice(&scores[n_scores-left_pad..]);
+            padded.extend_from_slice(&scores[..]);
+            padded.extend_from_slice(&scores[..right_pad]);
+        } else {
+            for _ in 0..left_pad {
+                padded.push(scores[0]);
+            }
+            padded.extend_from_slice(&scores[..]);
+            for _ in 0..right_pad {
+                padded.push(scores[n_scores-1]);
+            }
+        }
+        Ok(padded)
+    }
+    
+    /// returns the median score across all records in self,
+    /// handling missing scores as given by nan_policy
+    pub fn median(&self, nan_policy: NanPolicy) -> Result<f64, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        match nan_policy.select(&scores) {
+            Some(mut scores) => median(&mut scores),
+            None => Ok(f64::NAN),
+        }
+    }
+
+    /// returns the mean score across all records in self,
+    /// handling missing scores as given by nan_policy
+    pub fn mean(&self, nan_policy: NanPolicy) -> Result<f64, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        match nan_policy.select(&scores) {
+            Some(scores) => mean(&scores),
+            None => Ok(f64::NAN),
+        }
+    }
+
+    /// returns the median absolute deviation of scores across all records in self,
+    /// handling missing scores as given by nan_policy
+    pub fn mad(&self, nan_policy: NanPolicy) -> Result<f64, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        match nan_policy.select(&scores) {
+            Some(scores) => mad(&scores),
+            None => Ok(f64::NAN),
+        }
+    }
+
+    pub fn get_resolution(&self) -> usize {
+        self.data[1].start - self.data[0].start
+    }
+
+    /// calculates robust z-score across entire genome for each position, using the
+    /// median and the median absolute deviation, (score - median) / (1.4826 * MAD).
+    /// Missing scores are handled as given by nan_policy, and always remain missing.
+    /// Returns an error if the MAD is zero.
+    pub fn robust_z(
+            &self,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        self.robust_z_with(Dispersion::Mad, nan_policy)
+    }
+
+    /// calculates robust z-score across entire genome for each position, using the
+    /// median and the given dispersion estimator scaled to estimate the standard
+    /// deviation (see [Dispersion::sigma]). Missing scores are handled as given by
+    /// nan_policy, so that under [NanPolicy::Propagate] any missing score makes every
+    /// z-score NaN, and always remain missing. Returns an error if the dispersion is zero.
+    pub fn robust_z_with(
+            &self,
+            dispersion: Dispersion,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        if let Some(scores) = nan_policy.select(&self.fetch_scores()?) {
+            if dispersion.sigma(&scores)? == 0.0 {
+                return Err(Box::new(ZeroDispersion { dispersion }));
+            }
+        }
+        self.z_score(ZScore::Robust(dispersion), &ZScope::Genome, nan_policy)
+    }
+
+    /// calculates standard z-score, (score - mean) / SD, across entire genome for
+    /// each position. Missing scores are handled as given by nan_policy, and always
+    /// remain missing. Returns an error if the SD is zero.
+    pub fn standard_z(
+            &self,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        if let Some(scores) = nan_policy.select(&self.fetch_scores()?) {
+            if std_dev(&scores)? == 0.0 {
+                return Err(Box::new(ZeroDispersion { dispersion: Dispersion::Sd }));
+            }
+        }
+        self.z_score(ZScore::Standard, &ZScope::Genome, nan_policy)
+    }
+
+    /// calculates z-scores for each position, with the center and spread of the
+    /// scores computed separately within each group of records defined by scope.
+    /// Records in groups whose spread is zero are given NaN, rather than the
+    /// infinities that dividing by zero would produce. Missing scores within a group
+    /// are handled as given by nan_policy, and always remain missing.
+    pub fn z_score(
+            &self,
+            kind: ZScore,
+            scope: &ZScope,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        let mut zscores: Vec<f64> = vec![f64::NAN; self.len()];
+        let index = BEDGraphIndex::new(self);
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        match scope {
+            ZScope::Genome => groups.push((0..self.len()).collect()),
+            ZScope::Contig => {
+                for contig in self.get_contigs() {
+                    groups.push(index.contigs[&contig].clone());
+                }
+            },
+            ZScope::Regions(regions) => {
+                let mut assigned = vec![false; self.len()];
+                for region in regions.iter() {
+                    let group: Vec<usize> = index.overlap_indices(&region.seqname, region.start, region.end)
+                        .filter(|i| !assigned[*i])
+                        .collect();
+                    for i in &group {
+                        assigned[*i] = true;
+                    }
+                    groups.push(group);
+                }
+            },
+            ZScope::Window(window_bp) => {
+                let roll = |func: RollFn| {
+                    self.roll_fn_bp(*window_bp, WindowAlign::Center, false, 0.0, func, nan_policy)?
+                        .fetch_scores()
+                };
+                let (centers, spreads) = match kind {
+                    ZScore::Standard => (roll(RollFn::Mean)?, roll(RollFn::Sd)?),
+                    ZScore::Robust(dispersion) => {
+                        let spreads = match dispersion {
+                            Dispersion::Sd => roll(RollFn::Sd)?,
+                            Dispersion::Iqr => {
+                                let upper = roll(RollFn::Quantile(0.75))?;
+                                let lower = roll(RollFn::Quantile(0.25))?;
+                                upper.iter().zip(lower).map(|(u, l)| (u - l) / IQR_SCALE).collect()
+                            },
+                            _ => roll(RollFn::Custom(Box::new(move |window: &[f64]| {
+                                dispersion.sigma(window).unwrap_or(f64::NAN)
+                            })))?,
+                        };
+                        (roll(RollFn::Median)?, spreads)
+                    },
+                };
+                for (i,score) in scores.iter().enumerate() {
+                    zscores[i] = kind.apply(*score, centers[i], spreads[i]);
+                }
+            },
+        }
+
+        for group in groups {
+            if group.is_empty() {
+                continue;
+            }
+            let group_scores: Vec<f64> = group.iter().map(|i| scores[*i]).collect();
+            let (center, spread) = kind.center_and_spread(&group_scores, nan_policy)?;
+            for i in group {
+                zscores[i] = kind.apply(scores[i], center, spread);
+            }
+        }
+
+        let mut z_bgd = BEDGraphData{data: self.data.clone()};
+        z_bgd.set_scores(&zscores);
+        Ok(z_bgd)
+    }
+
+    /// calculats rolling function for each contig in the bedgraph file, over windows of
+    /// window_size records centered on each record. Even-sized windows include one more
+    /// record after the central record than before it.
+    pub fn roll_fn(
+            &self,
+            window_size: usize,
+            circular: bool,
+            func: RollFn,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        self.roll_fn_aligned(window_size, WindowAlign::Center, circular, func, nan_policy)
+    }
+
+    /// calculates rolling function for each contig in the bedgraph file, over windows
+    /// of window_size records aligned to each record as given by align. Missing scores
+    /// within a window are handled as given by nan_policy.
+    pub fn roll_fn_aligned(
+            &self,
+            window_size: usize,
+            align: WindowAlign,
+            circular: bool,
+            func: RollFn,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        if window_size == 0 {
+            return Err(Box::new(InvalidParameter {
+                message: String::from("window size must be greater than zero"),
+            }));
+        }
+        let (left_pad, right_pad) = match align {
+            WindowAlign::Center => ((window_size-1)/2, window_size/2),
+            WindowAlign::Left => (0, window_size-1),
+            WindowAlign::Right => (window_size-1, 0),
+        };
+        let contigs = self.get_contigs();
+        let mut records: Vec::<BEDGraphRecord> = Vec::with_capacity(self.len());
+        for contig in contigs {
+            let contig_bg = self.filter(
+                &contig,
+                &0,
+                &usize::MAX,
+            )?;
+            let padded_scores = contig_bg.get_asymmetric_padded_scores(
+                left_pad,
+                right_pad,
+                circular,
+            )?;
+
+            let results = func.roll(&padded_scores, window_size, nan_policy)?;
+            for (i,result) in results.iter().enumerate() {
+                let record = BEDGraphRecord::new(
+                    contig.to_string(),
+                    contig_bg.data[i].start,
+                    contig_bg.data[i].end,
+                    *result,
+                );
+                records.push( record );
+            }
+        }
+        Ok(BEDGraphData{data: records})
+    }
+
+    /// calculates rolling function for each contig in the bedgraph file, over windows
+    /// of window_bp base pairs aligned to each record as given by align. A window holds
+    /// the records whose midpoints lie within it, so results do not depend on the
+    /// track's resolution, and records may have variable widths.
+    ///
+    /// Windows are truncated at contig ends, unless circular is true, in which case they
+    /// wrap around to the other end of the contig. Where less than min_coverage, a fraction
+    /// between 0 and 1, of a window's bases are covered by its records, the result is NaN.
+    /// Records with missing scores are handled as given by nan_policy, and do not count
+    /// towards coverage if skipped. Each window is updated from the last as it slides along
+    /// the contig (see [RollFn::Custom] for the exception), so records within a contig
+    /// must not overlap.
+    pub fn roll_fn_bp(
+            &self,
+            window_bp: usize,
+            align: WindowAlign,
+            circular: bool,
+            min_coverage: f64,
+            func: RollFn,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        if window_bp == 0 {
+            return Err(Box::new(InvalidParameter {
+                message: String::from("window size must be greater than zero"),
+            }));
+        }
+        if !(0.0..=1.0).contains(&min_coverage) {
+            return Err(Box::new(InvalidParameter {
+                message: format!("minimum coverage must be between 0 and 1, but {} was given", min_coverage),
+            }));
+        }
+        let index = BEDGraphIndex::new(self);
+        let mut scores: Vec<f64> = vec![f64::NAN; self.len()];
+        // coordinates are doubled, so that record midpoints are integers
+        let width2 = 2 * window_bp as i64;
+
+        for contig in self.get_contigs() {
+            let idxs = &index.contigs[&contig];
+            let length2 = 2 * self.get_contig_length(&contig)? as i64;
+            // circular windows shorter than the contig are found in a copy of its records
+            // unrolled over three turns, so that each window is a contiguous run of records
+            let wraps = circular && width2 < length2;
+            let offsets: Vec<i64> = if wraps { vec![-length2, 0, length2] } else { vec![0] };
+            let mut bounds2: Vec<(i64, i64)> = Vec::with_capacity(offsets.len() * idxs.len());
+            let mut values: Vec<f64> = Vec::with_capacity(offsets.len() * idxs.len());
+            for offset in &offsets {
+                for i in idxs {
+                    let record = &self.data[*i];
+                    bounds2.push((2 * record.start as i64 + offset, 2 * record.end as i64 + offset));
+                    values.push(record.score);
+                }
+            }
+            let mids2: Vec<i64> = bounds2.iter().map(|(start2, end2)| (start2 + end2) / 2).collect();
+            let counted: Vec<bool> = values.iter()
+                .map(|x| !(nan_policy == NanPolicy::Skip && x.is_nan()))
+                .collect();
+            // bases covered by the counted records before each record; as records within a
+            // contig do not overlap, only a window's first and last records can be clipped
+            let mut covered_before: Vec<i64> = Vec::with_capacity(values.len() + 1);
+            covered_before.push(0);
+            for (k, (start2, end2)) in bounds2.iter().enumerate() {
+                let width = if counted[k] { end2 - start2 } else { 0 };
+                covered_before.push(covered_before[k] + width);
+            }
+
+            let mut window = RollingWindow::new(&values, &func, nan_policy)?;
+            let turn = if wraps { idxs.len() } else { 0 };
+            for (j,i) in idxs.iter().enumerate() {
+                let (start2, end2) = bounds2[turn + j];
+                let (lo2, hi2) = if circular && !wraps {
+                    (0, length2)
+                } else {
+                    let lo2 = match align {
+                        WindowAlign::Center => mids2[turn + j] - width2 / 2,
+                        WindowAlign::Left => start2,
+                        WindowAlign::Right => end2 - width2,
+                    };
+                    (lo2, lo2 + width2)
+                };
+                let first = mids2.partition_point(|x| *x < lo2);
+                let last = mids2.partition_point(|x| *x < hi2);
+                if first == last {
+                    continue;
+                }
+                let mut covered2 = covered_before[last] - covered_before[first];
+                if counted[first] {
+                    covered2 -= (lo2 - bounds2[first].0).max(0);
+                }
+                if counted[last - 1] {
+                    covered2 -= (bounds2[last - 1].1 - hi2).max(0);
+                }
+                window.advance(first, last)?;
+                if (covered2 as f64) >= min_coverage * width2 as f64 {
+                    scores[*i] = window.value()?;
+                }
+            }
+        }
+        let mut rolled = BEDGraphData{data: self.data.clone()};
+        rolled.set_scores(&scores);
+        Ok(rolled)
+    }
+
+    /// smooths the scores on each contig with the given filter. Widths given in bp are
+    /// converted to numbers of records using the width of each contig's first record,
+    /// so records should have uniform widths, as produced by [BEDGraphData::densify].
+    /// Contig ends are padded as in [BEDGraphData::get_padded_scores].
+    ///
+    /// Missing scores are handled as given by nan_policy. If propagated, a result is NaN
+    /// wherever a missing score falls within its kernel, or for exponential smoothing,
+    /// within the records whose weights are at least 1e-6. If skipped, kernel weights are
+    /// rescaled over the scores present, and exponential smoothing carries its average
+    /// over missing scores. Savitzky-Golay filters cannot skip missing scores.
+    pub fn smooth(
+            &self,
+            smoother: &Smoother,
+            circular: bool,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        if let Smoother::SavitzkyGolay { .. } = smoother {
+            if nan_policy == NanPolicy::Skip && self.data.iter().any(|x| x.score.is_nan()) {
+                return Err(Box::new(InvalidParameter {
+                    message: String::from("Savitzky-Golay filters cannot skip missing scores"),
+                }));
+            }
+        }
+        let contigs = self.get_contigs();
+        let mut records: Vec::<BEDGraphRecord> = Vec::with_capacity(self.len());
+        for contig in contigs {
+            let contig_bg = self.filter(
+                &contig,
+                &0,
+                &usize::MAX,
+            )?;
+            let resolution = contig_bg.data[0].width() as f64;
+            smoother.check(resolution)?;
+
+            let results: Vec<f64> = if let Smoother::Exponential { half_life_bp } = smoother {
+                let alpha = 1.0 - 0.5_f64.powf(resolution / half_life_bp);
+                // warm up over enough records for the initial value's weight to fall below 1e-6
+                let warmup = ((1e-6_f64.ln() / (1.0 - alpha).ln()).ceil() as usize)
+                    .min(contig_bg.len());
+                let padded_scores = contig_bg.get_asymmetric_padded_scores(warmup, 0, circular)?;
+                let mut ema = f64::NAN;
+                let mut results: Vec<f64> = Vec::with_capacity(padded_scores.len());
+                for score in &padded_scores {
+                    if ema.is_nan() {
+                        ema = *score;
+                    } else if !score.is_nan() {
+                        ema = alpha * score + (1.0 - alpha) * ema;
+                    }
+                    results.push(ema);
+                }
+                let mut results = results.split_off(warmup);
+                if nan_policy == NanPolicy::Propagate {
+                    let has_nan = nan_windows(&padded_scores, warmup + 1);
+                    for (result, nan) in results.iter_mut().zip(has_nan) {
+                        if nan {
+                            *result = f64::NAN;
+                        }
+                    }
+                }
+                results
+            } else {
+                let kernel = smoother.kernel(resolution)?;
+                let padded_scores = contig_bg.get_padded_scores(
+                    kernel.len() / 2,
+                    circular,
+                )?;
+                let kernel_sum: f64 = kernel.iter().sum();
+                padded_scores.windows(kernel.len())
+                    .map(|window| {
+                        let (sum, weight) = window.iter()
+                            .zip(kernel.iter())
+                            .filter(|(x, _)| !x.is_nan() || nan_policy == NanPolicy::Propagate)
+                            .fold((0.0, 0.0), |acc, (x, w)| (acc.0 + x * w, acc.1 + w));
+                        if weight == kernel_sum { sum } else { sum * kernel_sum / weight }
+                    })
+                    .collect()
+            };
+
+            for (i,result) in results.iter().enumerate() {
+                let record = BEDGraphRecord::new(
+                    contig.to_string(),
+                    contig_bg.data[i].start,
+                    contig_bg.data[i].end,
+                    *result,
+                );
+                records.push( record );
+            }
+        }
+        Ok(BEDGraphData{data: records})
+    }
+
+    /// calls enriched regions from records whose scores pass threshold. Passing records
+    /// on the same contig separated by no more than max_gap bp are merged into a region,
+    /// and regions shorter than min_length bp or containing fewer than min_bins passing
+    /// records are discarded. Regions are returned in contig order, sorted by start.
+    pub fn call_regions(
+            &self,
+            threshold: Threshold,
+            min_length: usize,
+            max_gap: usize,
+            min_bins: usize,
+    ) -> Result<Vec<CalledRegion>, Box<dyn Error>> {
+        let passing: Vec<bool> = match threshold {
+            Threshold::Absolute(cutoff) => self.iter().map(|x| x.score >= cutoff).collect(),
+            Threshold::RobustZ(cutoff) => {
+                self.robust_z(NanPolicy::Skip)?.iter().map(|x| x.score >= cutoff).collect()
+            },
+        };
+        let index = BEDGraphIndex::new(self);
+        let mut regions: Vec<CalledRegion> = Vec::new();
+
+        for contig in self.get_contigs() {
+            let mut members: Vec<&BEDGraphRecord> = Vec::new();
+            for i in index.contigs[&contig].iter().filter(|i| passing[**i]) {
+                let record = &self.data[*i];
+                if let Some(last) = members.last() {
+                    if record.start > last.end + max_gap {
+                        regions.extend(CalledRegion::from_members(&members));
+                        members.clear();
+                    }
+                }
+                members.push(record);
+            }
+            regions.extend(CalledRegion::from_members(&members));
+        }
+        regions.retain(|x| x.end - x.start >= min_length && x.n_bins >= min_bins);
+        Ok(regions)
+    }
+
+    /// calls peaks from treatment coverage in self against control coverage, in the
+    /// manner of MACS. The control is scaled to the treatment's total signal, then each
+    /// bin's local lambda is taken as described in [PeakCallParams::lambda_windows].
+    /// Each bin's treatment coverage, rounded to a count, is tested against a Poisson
+    /// distribution with that lambda, and the p-values are corrected by Benjamini-Hochberg.
+    /// Enriched bins are merged into peaks whose summits are their highest-coverage bins.
+    ///
+    /// Peaks have fold enrichment, -log10 p-value, and -log10 q-value at their summits as
+    /// their signal, p-, and q-values, and a score of int(-10 * log10 q), capped at 1000.
+    pub fn call_peaks(
+            &self,
+            control: &BEDGraphData,
+            params: &PeakCallParams,
+    ) -> Result<NarrowPeakData, Box<dyn Error>> {
+        let intervals = union_intervals(&[self, control]);
+        let treat: Vec<f64> = intervals.iter().map(|x| x.3[0].unwrap_or(0.0)).collect();
+        let ctrl: Vec<f64> = intervals.iter().map(|x| x.3[1].unwrap_or(0.0)).collect();
+        let widths: Vec<f64> = intervals.iter().map(|x| (x.2 - x.1) as f64).collect();
+        let treat_total: f64 = treat.iter().zip(widths.iter()).map(|(x, w)| x * w).sum();
+        let ctrl_total: f64 = ctrl.iter().zip(widths.iter()).map(|(x, w)| x * w).sum();
+        let scale = if ctrl_total > 0.0 { treat_total / ctrl_total } else { 1.0 };
+        let ctrl: Vec<f64> = ctrl.iter().map(|x| x * scale).collect();
+
+        // intervals are grouped by contig, each spanning [lo, hi) in intervals
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        for (i,interval) in intervals.iter().enumerate() {
+            match groups.last_mut() {
+                Some((_, hi)) if intervals[*hi - 1].0 == interval.0 => *hi = i + 1,
+                _ => groups.push((i, i + 1)),
+            }
+        }
+        let genome_bp: usize = groups.iter().map(|(_, hi)| intervals[*hi - 1].2).sum();
+        let lambda_bg = treat_total / genome_bp as f64;
+
+        let mut lambdas: Vec<f64> = Vec::with_capacity(intervals.len());
+        for (lo, hi) in &groups {
+            let ctg = &intervals[*lo..*hi];
+            let ctg_end = ctg[ctg.len() - 1].2 as f64;
+            let ends: Vec<f64> = ctg.iter().map(|x| x.2 as f64).collect();
+            let mut prefix: Vec<f64> = vec![0.0];
+            for (j,interval) in ctg.iter().enumerate() {
+                prefix.push(prefix[j] + ctrl[lo + j] * (interval.2 - interval.1) as f64);
+            }
+            // control signal over [0, pos)
+            let area_before = |pos: f64| -> f64 {
+                let j = ends.partition_point(|x| *x <= pos);
+                if j == ctg.len() {
+                    return prefix[j];
+                }
+                let start = ctg[j].1 as f64;
+                prefix[j] + ctrl[lo + j] * (pos - start).max(0.0)
+            };
+            for (j,interval) in ctg.iter().enumerate() {
+                let mid = (interval.1 + interval.2) as f64 / 2.0;
+                let mut lambda = lambda_bg.max(ctrl[lo + j]);
+                for window in &params.lambda_windows {
+                    let w_lo = (mid - *window as f64 / 2.0).max(0.0);
+                    let w_hi = (mid + *window as f64 / 2.0).min(ctg_end);
+                    if w_hi > w_lo {
+                        lambda = lambda.max((area_before(w_hi) - area_before(w_lo)) / (w_hi - w_lo));
+                    }
+                }
+                lambdas.push(lambda);
+            }
+        }
+
+        let mlog10_p: Vec<f64> = treat.iter().zip(lambdas.iter())
+            .map(|(t, lambda)| poisson_mlog10_sf(t.round().max(0.0) as u64, *lambda))
+            .collect();
+        let mlog10_q = p_adjust_mlog10(&mlog10_p, PAdjust::BenjaminiHochberg)?;
+        let q_threshold = -params.q_cutoff.log10();
+
+        // builds a peak from the enriched intervals making up a peak,
+        // if there are any and the peak is at least params.min_length bp long
+        let build_peak = |members: &[usize]| -> Option<NarrowPeakRecord> {
+            let first = &intervals[*members.first()?];
+            let last = &intervals[*members.last()?];
+            if last.2 - first.1 < params.min_length {
+                return None;
+            }
+            let summit = *members.iter()
+                .fold(&members[0], |best, x| if treat[*x] > treat[*best] { x } else { best });
+            let summit_pos = (intervals[summit].1 + intervals[summit].2) / 2;
+            Some(NarrowPeakRecord::new(
+                first.0.to_string(),
+                first.1,
+                last.2,
+                String::new(),
+                (10.0 * mlog10_q[summit]).floor().min(1000.0),
+                Strand::Unknown,
+                (treat[summit] + params.pseudocount) / (lambdas[summit] + params.pseudocount),
+                mlog10_p[summit],
+                mlog10_q[summit],
+                (summit_pos - first.1) as i64,
+            ))
+        };
+
+        let mut peaks: Vec<NarrowPeakRecord> = Vec::new();
+        for (lo, hi) in groups {
+            let mut members: Vec<usize> = Vec::new();
+            for i in (lo..hi).filter(|i| mlog10_q[*i] >= q_threshold) {
+                if let Some(last) = members.last() {
+                    if intervals[i].1 > intervals[*last].2 + params.max_gap {
+                        peaks.extend(build_peak(&members));
+                        members.clear();
+                    }
+                }
+                members.push(i);
+            }
+            peaks.extend(build_peak(&members));
+        }
+        for (i,peak) in peaks.iter_mut().enumerate() {
+            peak.name = format!("peak_{}", i + 1);
+        }
+        Ok(NarrowPeakData{data: peaks})
+    }
+
+    /// treats the scores in self as p-values, or as -log10 p-values if mlog10 is true,
+    /// and returns a track of the adjusted values on the same scale
+    pub fn p_adjust(
+            &self,
+            method: PAdjust,
+            mlog10: bool,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        let scores = self.fetch_scores()?;
+        let adjusted = if mlog10 {
+            p_adjust_mlog10(&scores, method)?
+        } else {
+            p_adjust(&scores, method)?
+        };
+        let mut adjusted_bgd = BEDGraphData{data: self.data.clone()};
+        adjusted_bgd.set_scores(&adjusted);
+        Ok(adjusted_bgd)
+    }
+
+    /// summarizes the scores in self over all contigs, with the given quantiles.
+    /// If bp_weighted is true, the mean, standard deviation, and quantiles weight each
+    /// record by its width, as if computed over every base pair rather than every record.
+    /// Records with NaN scores are counted, but otherwise excluded.
+    pub fn summary(
+            &self,
+            quantiles: &[f64],
+            bp_weighted: bool,
+    ) -> Result<ScoreSummary, Box<dyn Error>> {
+        check_probabilities(quantiles)?;
+        let records: Vec<&BEDGraphRecord> = self.data.iter().collect();
+        Ok(ScoreSummary::from_records("genome", &records, quantiles, bp_weighted))
+    }
+
+    /// returns the scores of self, excluding NaN, sorted and paired with their weights:
+    /// record widths if bp_weighted is true, and one otherwise. Returns an error if
+    /// no scores remain.
+    fn get_weighted_scores(&self, bp_weighted: bool) -> Result<Vec<(f64, usize)>, Box<dyn Error>> {
+        let mut pairs: Vec<(f64, usize)> = self.data.iter()
+            .filter(|x| !x.score.is_nan())
+            .map(|x| (x.score, if bp_weighted { x.width() } else { 1 }))
+            .collect();
+        if pairs.iter().all(|x| x.1 == 0) {
+            return Err(Box::new(InvalidParameter {
+                message: String::from("no scores other than NaN to summarize"),
+            }));
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(pairs)
+    }
+
+    /// returns a histogram of the scores in self, excluding NaN, weighting each
+    /// record by its width if bp_weighted is true
+    pub fn histogram(
+            &self,
+            bins: HistogramBins,
+            bp_weighted: bool,
+    ) -> Result<Histogram, Box<dyn Error>> {
+        let pairs = self.get_weighted_scores(bp_weighted)?;
+        let min = pairs[0].0;
+        let max = pairs[pairs.len() - 1].0;
+        let equal_width = |n_bins: usize| -> Vec<f64> {
+            (0..=n_bins)
+                .map(|i| min + (max - min) * i as f64 / n_bins as f64)
+                .collect()
+        };
+        let edges: Vec<f64> = match bins {
+            HistogramBins::Fixed(n_bins) | HistogramBins::Quantile(n_bins) if n_bins == 0 => {
+                return Err(Box::new(InvalidParameter {
+                    message: String::from("a histogram needs at least one bin"),
+                }));
+            },
+            HistogramBins::Fixed(n_bins) => equal_width(n_bins),
+            HistogramBins::Quantile(n_bins) => {
+                let mut edges: Vec<f64> = (0..=n_bins)
+                    .map(|i| weighted_quantile_sorted(&pairs, i as f64 / n_bins as f64))
+                    .collect();
+                edges.dedup();
+                if edges.len() == 1 {
+                    edges.push(max);
+                }
+                edges
+            },
+            HistogramBins::FreedmanDiaconis => {
+                let iqr = weighted_quantile_sorted(&pairs, 0.75)
+                    - weighted_quantile_sorted(&pairs, 0.25);
+                let n: usize = pairs.iter().map(|x| x.1).sum();
+                let bin_width = 2.0 * iqr / (n as f64).cbrt();
+                let n_bins = if bin_width > 0.0 {
+                    ((max - min) / bin_width).ceil().clamp(1.0, pairs.len() as f64)
+                } else if max > min {
+                    (n as f64).log2().ceil() + 1.0
+                } else {
+                    1.0
+                };
+                equal_width(n_bins as usize)
+            },
+        };
+        Ok(Histogram::from_sorted(&pairs, edges))
+    }
+
+    /// returns the empirical cumulative distribution function of the scores in self,
+    /// excluding NaN, weighting each record by its width if bp_weighted is true
+    pub fn ecdf(&self, bp_weighted: bool) -> Result<EmpiricalCdf, Box<dyn Error>> {
+        let pairs = self.get_weighted_scores(bp_weighted)?;
+        Ok(EmpiricalCdf::from_sorted(&pairs))
+    }
+
+    /// summarizes the scores in self separately for each contig, in order of
+    /// first appearance. See [BEDGraphData::summary].
+    pub fn contig_summaries(
+            &self,
+            quantiles: &[f64],
+            bp_weighted: bool,
+    ) -> Result<Vec<ScoreSummary>, Box<dyn Error>> {
+        check_probabilities(quantiles)?;
+        let mut order: Vec<&str> = Vec::new();
+        let mut by_contig: HashMap<&str, Vec<&BEDGraphRecord>> = HashMap::new();
+        for record in &self.data {
+            by_contig.entry(&record.seqname)
+                .or_insert_with(|| {
+                    order.push(&record.seqname);
+                    Vec::new()
+                }).push(record);
+        }
+        Ok(order.iter()
+            .map(|x| ScoreSummary::from_records(x, &by_contig[x], quantiles, bp_weighted))
+            .collect())
+    }
+
+    /// summarizes the signal in self over each region, in the manner of
+    /// bigWigAverageOverBed. Bedgraph records partially overlapping a region
+    /// contribute only their overlapping base pairs to the summary. Under
+    /// [NanPolicy::Skip], bases with missing scores are treated as uncovered, and under
+    /// [NanPolicy::Propagate], they make every statistic of their region NaN.
+    pub fn region_summary(
+            &self,
+            regions: &BEDData,
+            nan_policy: NanPolicy,
+    ) -> Result<Vec<RegionSummary>, Box<dyn Error>> {
+        let index = BEDGraphIndex::new(self);
+        let mut summaries: Vec<RegionSummary> = Vec::with_capacity(regions.len());
+        for region in regions.iter() {
+            let mut pairs: Vec<(f64, usize)> = index
+                .overlaps(&region.seqname, region.start, region.end)
+                .map(|(record, bp)| (record.score, bp))
+                .collect();
+            let has_nan = pairs.iter().any(|x| x.0.is_nan());
+            if nan_policy == NanPolicy::Skip {
+                pairs.retain(|x| !x.0.is_nan());
+            }
+            let mut summary = RegionSummary::from_pairs(region, &mut pairs);
+            if has_nan && nan_policy == NanPolicy::Propagate {
+                summary.propagate_nan();
+            }
+            summaries.push(summary);
+        }
+        Ok(summaries)
+    }
+
+    /// extracts binned signal around each region, returning a matrix with one row
+    /// per region and one column per bin. Each bin holds the mean score over its
+    /// covered bases, or NaN if none of its bases are covered. Rows for
+    /// reverse-strand regions are flipped, so that upstream bins always come first.
+    pub fn compute_matrix(
+            &self,
+            regions: &BEDData,
+            mode: &MatrixMode,
+            bin_size: usize,
+    ) -> Result<SignalMatrix, Box<dyn Error>> {
+        mode.check(bin_size)?;
+        let index = BEDGraphIndex::new(self);
+        let mut row_labels: Vec<String> = Vec::with_capacity(regions.len());
+        let mut values: Vec<f64> = Vec::new();
+        for region in regions.iter() {
+            for (start, end) in mode.bins(region, bin_size) {
+                let start = start.max(0) as usize;
+                let end = end.max(0) as usize;
+                let (sum, covered) = index.overlaps(&region.seqname, start, end)
+                    .fold((0.0, 0), |acc, (record, bp)| {
+                        (acc.0 + record.score * bp as f64, acc.1 + bp)
+                    });
+                values.push(sum / covered as f64);
+            }
+            let label = match &region.name {
+                Some(name) => name.to_string(),
+                None => format!("{}:{}-{}", region.seqname, region.start, region.end),
+            };
+            row_labels.push(label);
+        }
+        Ok(SignalMatrix{ row_labels, values, ncol: mode.ncol(bin_size) })
+    }
+
+    /// resamples self onto a uniform grid of bins of the given width, spanning each
+    /// contig from zero to its greatest end position. Unlike [BEDGraphData::unify_bins],
+    /// width need not evenly divide the original records, and bins may be wider than them.
+    ///
+    /// Uncovered bases within a bin are treated as having score fill, unless fill is NaN,
+    /// in which case they are ignored and bins with no covered bases are set to NaN.
+    /// Bases with missing scores are covered, but are ignored under [NanPolicy::Skip]
+    /// and make their bin NaN under [NanPolicy::Propagate], whatever the aggregation.
+    pub fn rebin(
+            &self,
+            width: usize,
+            agg: Aggregation,
+            fill: f64,
+            nan_policy: NanPolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        if width == 0 {
+            return Err(Box::new(InvalidParameter {
+                message: String::from("bin width must be greater than zero"),
+            }));
+        }
+        let index = BEDGraphIndex::new(self);
+        let mut records: Vec<BEDGraphRecord> = Vec::new();
+        for contig in self.get_genome()?.iter() {
+            let mut start = 0;
+            while start < contig.length {
+                let end = (start + width).min(contig.length);
+                let mut pairs: Vec<(f64, usize)> = index
+                    .overlaps(&contig.name, start, end)
+                    .map(|(record, bp)| (record.score, bp))
+                    .collect();
+                let covered: usize = pairs.iter().map(|x| x.1).sum();
+                if !fill.is_nan() && covered < end - start {
+                    pairs.push((fill, end - start - covered));
+                }
+                let has_nan = pairs.iter().any(|x| x.0.is_nan());
+                pairs.retain(|x| !x.0.is_nan());
+                let score = if has_nan && nan_policy == NanPolicy::Propagate {
+                    f64::NAN
+                } else {
+                    agg.apply(&mut pairs)
+                };
+                records.push(BEDGraphRecord::new(contig.name.to_string(), start, end, score));
+                start = end;
+            }
+        }
+        Ok(BEDGraphData{data: records})
+    }
+
+    /// returns a copy of self in which every base of every contig in genome is covered,
+    /// adding records with the given score for uncovered ranges, including those
+    /// between the last record on a contig and the contig's end.
+    /// Records are output in genome contig order, sorted by start position.
+    pub fn fill_gaps(
+            &self,
+            genome: &Genome,
+            value: f64,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        let index = BEDGraphIndex::new(self);
+        for contig in index.contigs.keys() {
+            genome.get_contig(contig)?;
+        }
+        let mut records: Vec<BEDGraphRecord> = Vec::with_capacity(self.len());
+        for contig in genome.iter() {
+            let mut prev_end = 0;
+            for (record, _) in index.overlaps(&contig.name, 0, usize::MAX) {
+                if record.start > prev_end {
+                    records.push(BEDGraphRecord::new(
+                        contig.name.to_string(),
+                        prev_end,
+                        record.start,
+                        value,
+                    ));
+                }
+                records.push(record.clone());
+                prev_end = record.end;
+            }
+            if contig.length > prev_end {
+                records.push(BEDGraphRecord::new(
+                    contig.name.to_string(),
+                    prev_end,
+                    contig.length,
+                    value,
+                ));
+            }
+        }
+        Ok(BEDGraphData{data: records})
+    }
+
+    /// returns a dense track with one record per bin of the given width across every
+    /// contig in genome, so that neighbouring records are always neighbouring bins.
+    /// Uncovered bases take the given score, and records not matching the bin grid
+    /// are combined by their base-weighted mean.
+    pub fn densify(
+            &self,
+            genome: &Genome,
+            width: usize,
+            value: f64,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        self.fill_gaps(genome, value)?.rebin(width, Aggregation::Mean, f64::NAN, NanPolicy::Propagate)
+    }
+
+    /// collapses runs of abutting records on the same contig with equal scores into
+    /// single records. Records are assumed to be sorted by contig and start position.
+    pub fn compress(&self) -> BEDGraphData {
+        let mut records: Vec<BEDGraphRecord> = Vec::with_capacity(self.len());
+        for record in self.iter() {
+            match records.last_mut() {
+                Some(prev) if prev.seqname == record.seqname
+                        && prev.end == record.start
+                        && prev.score == record.score => {
+                    prev.end = record.end;
+                },
+                _ => records.push(record),
+            }
+        }
+        BEDGraphData{data: records}
+    }
+
+    /// inverse of [BEDGraphData::fill_gaps]: drops records with the given score,
+    /// then compresses the remaining records with [BEDGraphData::compress]
+    pub fn sparsify(&self, value: f64) -> BEDGraphData {
+        let records: Vec<BEDGraphRecord> = self.iter()
+            .filter(|x| x.score != value)
+            .collect();
+        BEDGraphData{data: records}.compress()
+    }
+
+    /// applies func to the scores of self and other over each interval covered by both,
+    /// splitting records wherever either track has a breakpoint.
+    /// Intervals covered by only one of the tracks are omitted.
+    /// Infinite or NaN results of func for two finite scores, such as a [TrackOp::Ratio]
+    /// with a zero denominator, are handled record by record as given by non_finite,
+    /// while missing (NaN) scores always propagate.
+    pub fn combine<F>(
+            &self,
+            other: &BEDGraphData,
+            func: F,
+            non_finite: NonFinitePolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>>
+    where
+        F: Fn(f64, f64) -> f64,
+    {
+        let mut records: Vec<BEDGraphRecord> = Vec::new();
+        for (seqname, start, end, scores) in union_intervals(&[self, other]) {
+            if let (Some(a), Some(b)) = (scores[0], scores[1]) {
+                let mut score = func(a, b);
+                if a.is_finite() && b.is_finite() && !score.is_finite() {
+                    match non_finite {
+                        NonFinitePolicy::Keep => {},
+                        NonFinitePolicy::Nan => score = f64::NAN,
+                        NonFinitePolicy::Error => return Err(Box::new(InvalidParameter {
+                            message: format!(
+                                "combining scores {} and {} at {}:{}-{} gave {}",
+                                a, b, seqname, start, end, score,
+                            ),
+                        })),
+                    }
+                }
+                records.push(BEDGraphRecord::new(seqname, start, end, score));
+            }
+        }
+        Ok(BEDGraphData{data: records})
+    }
+
+    /// compares self to other, such as a ChIP track to its input, using the given
+    /// operation with self as the left operand. See [BEDGraphData::combine].
+    pub fn compare(
+            &self,
+            other: &BEDGraphData,
+            op: TrackOp,
+            non_finite: NonFinitePolicy,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+        self.combine(other, |a, b| op.apply(a, b), non_finite)
+    }
+
+    /// correlation between the scores of self and other, see [correlation_matrix]
+    pub fn correlation(
+            &self,
+            other: &BEDGraphData,
+            method: Correlation,
+            filter: &CorrelationFilter,
+    ) -> Result<f64, Box<dyn Error>> {
+        let matrix = correlation_matrix(&[self, other], &["a", "b"], method, filter)?;
+        Ok(matrix.get(0, 1))
+    }
+
+    pub fn unify_bins(
+            &self,
+            width: usize,
+    ) -> Result<BEDGraphData, Box<dyn Error>> {
+
+        let contigs = self.get_contigs();
+        let mut records: Vec::<BEDGraphRecord> = Vec::with_capacity(self.len());
+
+        for contig in contigs {
+            let contig_bg = self.filter(
+                &contig,
+                &0,
+                &usize::MAX,
+            )?;
+            let final_end = contig_bg.get_max_end();
+            for orig_record in contig_bg.iter() {
+                let mut new_records = orig_record.split_evenly(width, final_end)?;
+                records.append(&mut new_records)
+            }
+        }
+
+        Ok(BEDGraphData{data: records})
+    }
+}
+
+/// enables slicing of BEDData struct
+impl<Idx> std::ops::Index<Idx> for BEDData
+where
+    Idx: std::slice::SliceIndex<[BEDRecord]>,
+{
+    type Output = Idx::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+/// enables slicing of BEDGraphData struct
+impl<Idx> std::ops::Index<Idx> for BEDGraphData
+where
+    Idx: std::slice::SliceIndex<[BEDGraphRecord]>,
+{
+    type Output = Idx::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+/// Allows for iteration over a BEDGraphData struct
+///
+/// # Fields
+///
+/// * `loc` - Current location in the database
+/// * `bgd` - A reference to the [BEDGraphData]
+/// * `size` - The number of BEDGraphRecords in bgd
+pub struct BEDGraphDataIter<'a> {
+    loc: usize,
+    bgd: &'a BEDGraphData,
+    size: usize,
+}
+
+/// Enables iteration over the BEDGraphData. Returns a [BEDGraphRecord] as 
+/// each item.
+impl<'a> Iterator for BEDGraphDataIter<'a> {
+    type Item = BEDGraphRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.loc == self.size{
+            None
+        } else {
+            let out_rec = &self.bgd[self.loc];
+            self.loc += 1;
+            Some(BEDGraphRecord::new(
+                out_rec.seqname.to_string(),
+                out_rec.start,
+                out_rec.end,
+                out_rec.score,
+            ))
+        }
+    }
+}
+
+
+/// layout of the bins extracted for each region by [BEDGraphData::compute_matrix]
+#[derive(Debug, PartialEq, Clone)]
+pub enum MatrixMode {
+    /// bins spanning upstream bp before and downstream bp after a single
+    /// reference point in each region, such as its 5' end (a TSS) or its center
+    ReferencePoint {
+        anchor: Anchor,
+        upstream: usize,
+        downstream: usize,
+    },
+    /// bins spanning upstream bp before each region's 5' end and downstream bp after
+    /// its 3' end, with the region body itself scaled to body_length bp
+    ScaleRegions {
+        upstream: usize,
+        body_length: usize,
+        downstream: usize,
+    },
+}
+
+impl MatrixMode {
+    /// returns an error unless every segment of the layout is a whole number of bins
+    fn check(&self, bin_size: usize) -> Result<(), Box<dyn Error>> {
+        let lengths = match self {
+            MatrixMode::ReferencePoint { upstream, downstream, .. } => vec![*upstream, *downstream],
+            MatrixMode::ScaleRegions { upstream, body_length, downstream } => {
+                vec![*upstream, *body_length, *downstream]
+            },
+        };
+        if bin_size == 0 || lengths.iter().any(|x| !x.is_multiple_of(bin_size)) {
+            return Err(Box::new(InvalidParameter {
+                message: format!(
+                    "matrix flank and body lengths must be multiples of the bin size, {}",
+                    bin_size,
+                ),
+            }));
+        }
+        Ok(())
+    }
+
+    fn ncol(&self, bin_size: usize) -> usize {
+        match self {
+            MatrixMode::ReferencePoint { upstream, downstream, .. } => (upstream + downstream) / bin_size,
+            MatrixMode::ScaleRegions { upstream, body_length, downstream } => {
+                (upstream + body_length + downstream) / bin_size
+            },
+        }
+    }
+
+    /// returns the bins for region, ordered from upstream to downstream
+    fn bins(&self, region: &BEDRecord, bin_size: usize) -> Vec<(i64, i64)> {
+        let reverse = region.is_reverse();
+        let start = region.start as i64;
+        let end = region.end as i64;
+        match self {
+            MatrixMode::ReferencePoint { anchor, upstream, downstream } => {
+                let point = match (anchor, reverse) {
+                    (Anchor::FivePrime, false) | (Anchor::ThreePrime, true) => start,
+                    (Anchor::FivePrime, true) | (Anchor::ThreePrime, false) => end,
+                    (Anchor::Center, _) => (start + end) / 2,
+                };
+                flank_bins(point, *upstream, *downstream, bin_size, reverse)
+            },
+            MatrixMode::ScaleRegions { upstream, body_length, downstream } => {
+                let (five_prime, three_prime) = if reverse { (end, start) } else { (start, end) };
+                let mut bins = flank_bins(five_prime, *upstream, 0, bin_size, reverse);
+                let n_body = (body_length / bin_size) as i64;
+                let length = end - start;
+                for j in 0..n_body {
+                    let lo = j * length / n_body;
+                    let hi = (j + 1) * length / n_body;
+                    if reverse {
+                        bins.push((end - hi, end - lo));
+                    } else {
+                        bins.push((start + lo, start + hi));
+                    }
+                }
+                bins.append(&mut flank_bins(three_prime, 0, *downstream, bin_size, reverse));
+                bins
+            },
+        }
+    }
+}
+
+/// bins of width bin_size from upstream bp before point to downstream bp after it,
+/// with upstream lying at higher coordinates when reverse is true
+fn flank_bins(
+        point: i64,
+        upstream: usize,
+        downstream: usize,
+        bin_size: usize,
+        reverse: bool,
+) -> Vec<(i64, i64)> {
+    let n_bins = ((upstream + downstream) / bin_size) as i64;
+    let bin_size = bin_size as i64;
+    let upstream = upstream as i64;
+    (0..n_bins).map(|k| {
+        if reverse {
+            (point + upstream - (k + 1) * bin_size, point + upstream - k * bin_size)
+        } else {
+            (point - upstream + k * bin_size, point - upstream + (k + 1) * bin_size)
+        }
+    }).collect()
+}
+
+/// statistic computed over each column of a [SignalMatrix]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColumnStat {
+    Mean,
+    Median,
+    /// standard error of the mean
+    Sem,
+}
+
+/// binned signal matrix with one labeled row per region, as
+/// produced by [BEDGraphData::compute_matrix]
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignalMatrix {
+    row_labels: Vec<String>,
+    values: Vec<f64>,
+    ncol: usize,
+}
+
+impl SignalMatrix {
+    pub fn nrow(&self) -> usize {
+        self.row_labels.len()
+    }
+
+    pub fn ncol(&self) -> usize {
+        self.ncol
+    }
+
+    pub fn row_labels(&self) -> &[String] {
+        &self.row_labels
+    }
+
+    /// returns the values in row i
+    pub fn row(&self, i: usize) -> &[f64] {
+        &self.values[i * self.ncol..(i + 1) * self.ncol]
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        self.values[i * self.ncol + j]
+    }
+
+    /// summarizes each column, ignoring NaN bins, for plotting a metaprofile.
+    /// Columns without any non-NaN values summarize to NaN.
+    pub fn column_summary(&self, stat: ColumnStat) -> Result<Vec<f64>, Box<dyn Error>> {
+        let mut summary: Vec<f64> = Vec::with_capacity(self.ncol);
+        for j in 0..self.ncol {
+            let mut column: Vec<f64> = (0..self.nrow())
+                .map(|i| self.get(i, j))
+                .filter(|x| !x.is_nan())
+                .collect();
+            if column.is_empty() {
+                summary.push(f64::NAN);
+                continue;
+            }
+            let value = match stat {
+                ColumnStat::Mean => mean(&column)?,
+                ColumnStat::Median => median(&mut column)?,
+                ColumnStat::Sem => {
+                    let n = column.len() as f64;
+                    let col_mean = mean(&column)?;
+                    let ss: f64 = column.iter().map(|x| (x - col_mean).powi(2)).sum();
+                    (ss / (n - 1.0)).sqrt() / n.sqrt()
+                },
+            };
+            summary.push(value);
+        }
+        Ok(summary)
+    }
+
+    /// Writes the matrix to a tab-delimited file, one row per region with its label first
+    pub fn to_file(&self, fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+        let outf = File::create(fname)?;
+        let mut writer = BufWriter::new(outf);
+        write!(writer, "{}", self)?;
+        Ok(())
+    }
+}
+
+/// Implement `Display` for `SignalMatrix`.
+impl fmt::Display for SignalMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i,label) in self.row_labels.iter().enumerate() {
+            write!(f, "{}", label)?;
+            for value in self.row(i) {
+                write!(f, "\t{}", value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// how the scores of bedgraph records overlapping a bin are combined into
+/// a single score, with each record weighted by its overlapping base pairs
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Aggregation {
+    Mean,
+    /// sum of each score multiplied by its overlapping bp, i.e., the area under the signal
+    Sum,
+    Max,
+    Min,
+    Median,
+}
+
+impl Aggregation {
+    /// aggregates (score, overlapping bp) pairs, returning NaN if pairs is empty
+    fn apply(&self, pairs: &mut [(f64, usize)]) -> f64 {
+        if pairs.is_empty() {
+            return f64::NAN;
+        }
+        match self {
+            Aggregation::Mean => {
+                let covered: usize = pairs.iter().map(|x| x.1).sum();
+                pairs.iter().map(|x| x.0 * x.1 as f64).sum::<f64>() / covered as f64
+            },
+            Aggregation::Sum => pairs.iter().map(|x| x.0 * x.1 as f64).sum(),
+            Aggregation::Max => pairs.iter().map(|x| x.0).fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Min => pairs.iter().map(|x| x.0).fold(f64::INFINITY, f64::min),
+            Aggregation::Median => weighted_median(pairs),
+        }
+    }
+}
+
+/// struct to define a single line of a narrowPeak file
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct NarrowPeakRecord {
+    seqname: String,
+    start: usize,
+    end: usize,
+    name: String,
+    score: f64,
+    strand: Strand,
+    signal_value: f64,
+    p_value: f64,
+    q_value: f64,
+    peak: i64,
+}
+
+impl NarrowPeakRecord {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        seqname: String,
+        start: usize,
+        end: usize,
+        name: String,
+        score: f64,
+        strand: Strand,
+        signal_value: f64,
+        p_value: f64,
+        q_value: f64,
+        peak: i64,
+    ) -> NarrowPeakRecord {
+        NarrowPeakRecord {
+            seqname,
+            start,
+            end,
+            name,
+            score,
+            strand,
+            signal_value,
+            p_value,
+            q_value,
+            peak,
+        }
+    }
+
+    pub fn seqname(&self) -> &str {
+        &self.seqname
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    pub fn strand(&self) -> Strand {
+        self.strand
+    }
+
+    pub fn signal_value(&self) -> f64 {
+        self.signal_value
+    }
+
+    /// -log10 p-value, or -1 if not computed
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// -log10 q-value, or -1 if not computed
+    pub fn q_value(&self) -> f64 {
+        self.q_value
+    }
+
+    /// summit offset from start, or -1 if no summit was called
+    pub fn peak(&self) -> i64 {
+        self.peak
+    }
+
+    /// returns the absolute position of the summit, or the midpoint
+    /// if no summit was called
+    pub fn summit(&self) -> usize {
+        if self.peak < 0 {
+            (self.start + self.end) / 2
+        } else {
+            self.start + self.peak as usize
+        }
+    }
+}
+
+/// Implement `Display` for `NarrowPeakRecord`.
+impl fmt::Display for NarrowPeakRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.seqname,
+            self.start,
+            self.end,
+            self.name,
+            self.score,
+            self.strand,
+            self.signal_value,
+            self.p_value,
+            self.q_value,
+            self.peak,
+        )
+    }
+}
+
+/// holds a narrowPeak file
+pub struct NarrowPeakData {
+    data: Vec<NarrowPeakRecord>,
+}
+
+impl NarrowPeakData {
+
+    pub fn from_record_vec(rec_vec: Vec<NarrowPeakRecord>) -> NarrowPeakData {
+        NarrowPeakData{ data: rec_vec }
+    }
+
+    /// Read a narrowPeak file
+    pub fn from_file(fname: &path::PathBuf) -> Result<NarrowPeakData, Box<dyn Error>> {
+
+        let file = File::open(fname).unwrap_or_else(|err| {
+            eprintln!("Problem reading narrowPeak file {:?}: {}", fname, err);
+            process::exit(1);
+        });
+        // open buffered reader to narrowPeak file
+        let buf_reader = BufReader::new(file);
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_reader(buf_reader);
+        let mut records: Vec<NarrowPeakRecord> = Vec::new();
+
+        for result in rdr.deserialize() {
+            let record = result.unwrap_or_else(|err| {
+                eprintln!("Problem with your narrowPeak records. Is {:?} a properly-formed narrowPeak file?: {}", fname, err);
+                process::exit(1);
+            });
+            records.push(record);
+        }
+        Ok(NarrowPeakData{ data: records })
+    }
+
+    /// Writes the narrowPeak data to stdout
+    pub fn print(&self) -> Result<(), Box<dyn Error>> {
+        let mut stdout = stdout();
+        for record in &self.data {
+            write!(stdout, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the narrowPeak data to a narrowPeak file
+    pub fn to_file(&self, fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+        let outf = File::create(fname)?;
+        let mut writer = BufWriter::new(outf);
+
+        for record in &self.data {
+            write!(writer, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Returns number of records in self
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if self contains no records
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterate over the records in self
+    pub fn iter(&self) -> std::slice::Iter<'_, NarrowPeakRecord> {
+        self.data.iter()
+    }
+
+    /// returns a copy of self with the q-value column set to the -log10 adjusted
+    /// values of the -log10 p-value column. Peaks with p-values of -1, meaning
+    /// not computed, are excluded from the correction and keep q-values of -1.
+    pub fn p_adjust(&self, method: PAdjust) -> Result<NarrowPeakData, Box<dyn Error>> {
+        let mlog10_p: Vec<f64> = self.iter()
+            .map(|x| if x.p_value < 0.0 { f64::NAN } else { x.p_value })
+            .collect();
+        let mlog10_q = p_adjust_mlog10(&mlog10_p, method)?;
+        let records: Vec<NarrowPeakRecord> = self.iter()
+            .zip(mlog10_q.iter())
+            .map(|(x, q)| {
+                let mut record = x.clone();
+                record.q_value = if q.is_nan() { -1.0 } else { *q };
+                record
+            }).collect();
+        Ok(NarrowPeakData{data: records})
+    }
+
+    /// pairs each peak in self with at most one overlapping peak in other, preferring
+    /// the pairs with the most overlap, and returns the index of each pair
+    fn match_overlapping(&self, other: &NarrowPeakData) -> Vec<(usize, usize)> {
+        let mut other_by_contig: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i,x) in other.iter().enumerate() {
+            other_by_contig.entry(&x.seqname).or_default().push(i);
+        }
+        for indices in other_by_contig.values_mut() {
+            indices.sort_by_key(|i| other.data[*i].start);
+        }
+        let max_width = other.iter().map(|x| x.end - x.start).max().unwrap_or(0);
+
+        // (overlap, index in self, index in other) of every overlapping pair
+        let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+        for (i,x) in self.iter().enumerate() {
+            if let Some(indices) = other_by_contig.get(x.seqname.as_str()) {
+                let first = indices.partition_point(|j| {
+                    other.data[*j].start + max_width <= x.start
+                });
+                for j in &indices[first..] {
+                    let y = &other.data[*j];
+                    if y.start >= x.end {
+                        break;
+                    }
+                    let overlap = x.end.min(y.end).saturating_sub(x.start.max(y.start));
+                    if overlap > 0 {
+                        candidates.push((overlap, i, *j));
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+        let mut used_self = vec![false; self.len()];
+        let mut used_other = vec![false; other.len()];
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        for (_, i, j) in candidates {
+            if !used_self[i] && !used_other[j] {
+                used_self[i] = true;
+                used_other[j] = true;
+                pairs.push((i, j));
+            }
+        }
+        pairs.sort();
+        pairs
+    }
+
+    /// estimates the irreproducible discovery rate (IDR) of Li et al. (2011) between
+    /// self and other, two replicate peak sets. Overlapping peaks are paired one-to-one,
+    /// and peaks without a partner are dropped. The ranks of the paired peaks within
+    /// each replicate are fitted to a Gaussian copula mixture of a reproducible and an
+    /// irreproducible component by EM. Records are returned in order of increasing
+    /// local IDR, with the merged peaks named idr_peak_N.
+    pub fn idr(
+            &self,
+            other: &NarrowPeakData,
+            params: &IdrParams,
+    ) -> Result<IdrData, Box<dyn Error>> {
+        if params.sigma <= 0.0
+                || params.mix <= 0.0 || params.mix >= 1.0
+                || params.rho <= -1.0 || params.rho >= 1.0 {
+            return Err(Box::new(InvalidParameter {
+                message: String::from(
+                    "IDR starting values need sigma > 0, 0 < mix < 1, and -1 < rho < 1"
+                ),
+            }));
+        }
+        let pairs = self.match_overlapping(other);
+        if pairs.len() < 3 {
+            return Err(Box::new(InvalidParameter {
+                message: format!(
+                    "IDR needs at least 3 overlapping peak pairs, but {} were found",
+                    pairs.len(),
+                ),
+            }));
+        }
+
+        let n = pairs.len() as f64;
+        let values1: Vec<f64> = pairs.iter().map(|(i,_)| params.rank_by.value(&self.data[*i])).collect();
+        let values2: Vec<f64> = pairs.iter().map(|(_,j)| params.rank_by.value(&other.data[*j])).collect();
+        let u1: Vec<f64> = average_ranks(&values1).iter().map(|r| r / (n + 1.0)).collect();
+        let u2: Vec<f64> = average_ranks(&values2).iter().map(|r| r / (n + 1.0)).collect();
+        let (model, posterior) = IdrModel::fit(&u1, &u2, params);
+
+        let local_idr: Vec<f64> = posterior.iter().map(|e| 1.0 - e).collect();
+        let order: Vec<usize> = (0..pairs.len())
+            .sorted_by(|a, b| local_idr[*a].total_cmp(&local_idr[*b]))
+            .collect();
+
+        let mut records: Vec<IdrRecord> = Vec::new();
+        let mut cumulative = 0.0;
+        for (rank,k) in order.iter().enumerate() {
+            cumulative += local_idr[*k];
+            let global_idr = cumulative / (rank + 1) as f64;
+            let rep1 = &self.data[pairs[*k].0];
+            let rep2 = &other.data[pairs[*k].1];
+            let start = rep1.start.min(rep2.start);
+            let end = rep1.end.max(rep2.end);
+            let summit = (rep1.summit() + rep2.summit()) / 2;
+            let score = (-125.0 * global_idr.log2()).floor().min(1000.0);
+            let peak = NarrowPeakRecord::new(
+                rep1.seqname.to_string(),
+                start,
+                end,
+                format!("idr_peak_{}", rank + 1),
+                score,
+                rep1.strand,
+                (rep1.signal_value + rep2.signal_value) / 2.0,
+                (rep1.p_value + rep2.p_value) / 2.0,
+                (rep1.q_value + rep2.q_value) / 2.0,
+                (summit - start) as i64,
+            );
+            records.push(IdrRecord {
+                peak,
+                local_idr: local_idr[*k],
+                global_idr,
+                rep1: rep1.clone(),
+                rep2: rep2.clone(),
+            });
+        }
+        Ok(IdrData{model, data: records})
+    }
+
+    /// labels each peak with genes and genomic context as in [BEDData::annotate_regions],
+    /// using each peak's summit rather than its midpoint
+    pub fn annotate_regions(
+            &self,
+            annotation: &GffData,
+            params: &AnnotationParams,
+    ) -> AnnotationData {
+        let regions: Vec<(BEDRecord, usize)> = self.to_bed().data.into_iter()
+            .zip(self.iter())
+            .map(|(bed, peak)| (bed, peak.summit()))
+            .collect();
+        annotate_positions(regions, &annotation.gene_models(), params)
+    }
+
+    /// returns the peaks as bed records, keeping their names, scores, and strands
+    pub fn to_bed(&self) -> BEDData {
+        let records: Vec<BEDRecord> = self.iter()
+            .map(|x| BEDRecord::new_stranded(
+                x.seqname.to_string(),
+                x.start,
+                x.end,
+                Some(x.name.to_string()),
+                Some(x.score),
+                x.strand,
+            )).collect();
+        BEDData{data: records}
+    }
+}
+
+/// enables slicing of NarrowPeakData struct
+impl<Idx> std::ops::Index<Idx> for NarrowPeakData
+where
+    Idx: std::slice::SliceIndex<[NarrowPeakRecord]>,
+{
+    type Output = Idx::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+/// parameters of the Poisson peak caller, [BEDGraphData::call_peaks]
+#[derive(Debug, PartialEq, Clone)]
+pub struct PeakCallParams {
+    /// widths, in bp, of the windows of control signal around each bin from which local
+    /// background means are calculated. The greatest of these, the control signal at the
+    /// bin itself, and the genome-wide treatment mean is used as the bin's lambda.
+    pub lambda_windows: Vec<usize>,
+    /// bins with Benjamini-Hochberg q-values at or below this value are enriched
+    pub q_cutoff: f64,
+    /// enriched bins separated by no more than this many bp are merged into a peak
+    pub max_gap: usize,
+    /// peaks shorter than this many bp are discarded
+    pub min_length: usize,
+    /// added to treatment and lambda when calculating fold enrichment
+    pub pseudocount: f64,
+}
+
+impl Default for PeakCallParams {
+    fn default() -> PeakCallParams {
+        PeakCallParams {
+            lambda_windows: vec![1_000, 5_000, 10_000],
+            q_cutoff: 0.05,
+            max_gap: 0,
+            min_length: 0,
+            pseudocount: 1.0,
+        }
+    }
+}
+
+/// narrowPeak column by which peaks are ranked for [NarrowPeakData::idr]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PeakRank {
+    SignalValue,
+    PValue,
+    QValue,
+}
+
+impl PeakRank {
+    fn value(&self, record: &NarrowPeakRecord) -> f64 {
+        match self {
+            PeakRank::SignalValue => record.signal_value,
+            PeakRank::PValue => record.p_value,
+            PeakRank::QValue => record.q_value,
+        }
+    }
+}
+
+/// parameters of the IDR analysis, [NarrowPeakData::idr]. The mean, standard
+/// deviation, correlation, and mixing proportion are starting values for the
+/// reproducible component of the copula mixture model.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdrParams {
+    /// column used to rank the peaks within each replicate
+    pub rank_by: PeakRank,
+    pub mu: f64,
+    pub sigma: f64,
+    pub rho: f64,
+    pub mix: f64,
+    /// maximum number of EM iterations
+    pub max_iter: usize,
+    /// EM stops once the log-likelihood changes by less than this between iterations
+    pub tolerance: f64,
+}
+
+impl Default for IdrParams {
+    fn default() -> IdrParams {
+        IdrParams {
+            rank_by: PeakRank::SignalValue,
+            mu: 0.1,
+            sigma: 1.0,
+            rho: 0.2,
+            mix: 0.5,
+            max_iter: 1_000,
+            tolerance: 1e-6,
+        }
+    }
+}
+
+// bounds applied to the parameters of the reproducible component after each M-step.
+// The copula likelihood grows without bound as sigma falls to 0 or |rho| rises to 1,
+// where the component collapses onto a handful of nearly identical pairs, so sigma and
+// rho are kept away from those limits. mu >= 0 keeps it the higher-ranked of the two
+// components, the upper bounds on mu and sigma stop it drifting away from all pairs, and
+// the mixing proportion is kept away from 0 and 1 so that neither component vanishes.
+const IDR_MIN_MIX: f64 = 0.01;
+const IDR_MAX_MU: f64 = 5.0;
+const IDR_MIN_SIGMA: f64 = 0.2;
+const IDR_MAX_SIGMA: f64 = 20.0;
+const IDR_MAX_RHO: f64 = 0.99;
+
+/// fitted Gaussian copula mixture model of an IDR analysis
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct IdrModel {
+    mu: f64,
+    sigma: f64,
+    rho: f64,
+    mix: f64,
+    log_likelihood: f64,
+    n_iter: usize,
+}
+
+impl IdrModel {
+    /// mean of the reproducible component
+    pub fn mu(&self) -> f64 {
+        self.mu
+    }
+
+    /// standard deviation of the reproducible component
+    pub fn sigma(&self) -> f64 {
+        self.sigma
+    }
+
+    /// correlation between replicates in the reproducible component
+    pub fn rho(&self) -> f64 {
+        self.rho
+    }
+
+    /// proportion of peaks belonging to the reproducible component
+    pub fn mix(&self) -> f64 {
+        self.mix
+    }
+
+    /// copula log-likelihood of the fitted model
+    pub fn log_likelihood(&self) -> f64 {
+        self.log_likelihood
+    }
+
+    /// number of EM iterations run
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    /// probability densities of the reproducible and irreproducible components at (z1, z2)
+    fn densities(&self, z1: f64, z2: f64) -> (f64, f64) {
+        let a = (z1 - self.mu) / self.sigma;
+        let b = (z2 - self.mu) / self.sigma;
+        let one_m_rho2 = 1.0 - self.rho * self.rho;
+        let f1 = (-(a * a - 2.0 * self.rho * a * b + b * b) / (2.0 * one_m_rho2)).exp()
+            / (2.0 * std::f64::consts::PI * self.sigma * self.sigma * one_m_rho2.sqrt());
+        (f1, normal_pdf(z1) * normal_pdf(z2))
+    }
+
+    /// marginal density of the mixture at z
+    fn marginal_pdf(&self, z: f64) -> f64 {
+        self.mix * normal_pdf((z - self.mu) / self.sigma) / self.sigma
+            + (1.0 - self.mix) * normal_pdf(z)
+    }
+
+    /// maps each u in (0, 1) to the z at which the marginal cumulative distribution of
+    /// the mixture equals u, interpolating between points on a grid
+    fn pseudo_values(&self, u: &[f64]) -> Vec<f64> {
+        const N_POINTS: usize = 2_000;
+        let lo = (self.mu - 6.0 * self.sigma).min(-6.0);
+        let hi = (self.mu + 6.0 * self.sigma).max(6.0);
+        let step = (hi - lo) / (N_POINTS - 1) as f64;
+        let grid: Vec<f64> = (0..N_POINTS).map(|i| lo + i as f64 * step).collect();
+        let cdf: Vec<f64> = grid.iter()
+            .map(|z| self.mix * normal_cdf((z - self.mu) / self.sigma)
+                + (1.0 - self.mix) * normal_cdf(*z))
+            .collect();
+        u.iter().map(|x| {
+            let i = cdf.partition_point(|c| c < x);
+            if i == 0 {
+                grid[0]
+            } else if i == N_POINTS {
+                grid[N_POINTS - 1]
+            } else {
+                let frac = (x - cdf[i - 1]) / (cdf[i] - cdf[i - 1]);
+                grid[i - 1] + frac * step
+            }
+        }).collect()
+    }
+
+    /// fits the model to the paired pseudo-observations u1 and u2 by EM, recomputing
+    /// the pseudo-values from the updated marginal at each iteration. Returns the
+    /// fitted model and the posterior probability that each pair is reproducible.
+    fn fit(
+            u1: &[f64],
+            u2: &[f64],
+            params: &IdrParams,
+    ) -> (IdrModel, Vec<f64>) {
+        let mut model = IdrModel {
+            mu: params.mu,
+            sigma: params.sigma,
+            rho: params.rho,
+            mix: params.mix,
+            log_likelihood: f64::NEG_INFINITY,
+            n_iter: 0,
+        };
+        let n = u1.len();
+        let mut posterior = vec![0.0; n];
+        for iter in 1..=params.max_iter {
+            let z1 = model.pseudo_values(u1);
+            let z2 = model.pseudo_values(u2);
+
+            // E-step, accumulating the copula log-likelihood of the current model
+            let mut log_likelihood = 0.0;
+            for i in 0..n {
+                let (f1, f0) = model.densities(z1[i], z2[i]);
+                let joint = model.mix * f1 + (1.0 - model.mix) * f0;
+                posterior[i] = model.mix * f1 / joint;
+                log_likelihood += joint.ln()
+                    - model.marginal_pdf(z1[i]).ln()
+                    - model.marginal_pdf(z2[i]).ln();
+            }
+
+            // M-step
+            let total: f64 = posterior.iter().sum();
+            let mu = posterior.iter().enumerate()
+                .map(|(i,e)| e * (z1[i] + z2[i]))
+                .sum::<f64>() / (2.0 * total);
+            let var = posterior.iter().enumerate()
+                .map(|(i,e)| e * ((z1[i] - mu).powi(2) + (z2[i] - mu).powi(2)))
+                .sum::<f64>() / (2.0 * total);
+            let cov = posterior.iter().enumerate()
+                .map(|(i,e)| e * (z1[i] - mu) * (z2[i] - mu))
+                .sum::<f64>() / total;
+            model.mix = (total / n as f64).clamp(IDR_MIN_MIX, 1.0 - IDR_MIN_MIX);
+            model.mu = mu.clamp(0.0, IDR_MAX_MU);
+            model.sigma = var.sqrt().clamp(IDR_MIN_SIGMA, IDR_MAX_SIGMA);
+            model.rho = (cov / var).clamp(-IDR_MAX_RHO, IDR_MAX_RHO);
+            model.n_iter = iter;
+
+            let converged = (log_likelihood - model.log_likelihood).abs() < params.tolerance;
+            model.log_likelihood = log_likelihood;
+            if converged {
+                break;
+            }
+        }
+        (model, posterior)
+    }
+}
+
+/// a pair of overlapping replicate peaks, merged, with its IDR values
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdrRecord {
+    peak: NarrowPeakRecord,
+    local_idr: f64,
+    global_idr: f64,
+    rep1: NarrowPeakRecord,
+    rep2: NarrowPeakRecord,
+}
+
+impl IdrRecord {
+    /// the merged peak, spanning both replicate peaks, with the mean of their signal
+    /// values, p-values, q-values, and summits. Its score is
+    /// min(int(-125 * log2(global IDR)), 1000).
+    pub fn peak(&self) -> &NarrowPeakRecord {
+        &self.peak
+    }
+
+    /// posterior probability that the pair belongs to the irreproducible component
+    pub fn local_idr(&self) -> f64 {
+        self.local_idr
+    }
+
+    /// expected fraction of irreproducible pairs among all pairs with a local IDR
+    /// at or below this pair's
+    pub fn global_idr(&self) -> f64 {
+        self.global_idr
+    }
+
+    pub fn rep1(&self) -> &NarrowPeakRecord {
+        &self.rep1
+    }
+
+    pub fn rep2(&self) -> &NarrowPeakRecord {
+        &self.rep2
+    }
+}
+
+/// Implement `Display` for `IdrRecord`, with the columns of the merged narrowPeak record
+/// followed by -log10 local and global IDR and then the start, end, signal value, and
+/// summit of each replicate peak
+impl fmt::Display for IdrRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mlog10 = |x: f64| if x >= 1.0 { 0.0 } else { -x.log10() };
+        writeln!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.peak.seqname,
+            self.peak.start,
+            self.peak.end,
+            self.peak.name,
+            self.peak.score,
+            self.peak.strand,
+            self.peak.signal_value,
+            self.peak.p_value,
+            self.peak.q_value,
+            self.peak.peak,
+            mlog10(self.local_idr),
+            mlog10(self.global_idr),
+            self.rep1.start,
+            self.rep1.end,
+            self.rep1.signal_value,
+            self.rep1.summit(),
+            self.rep2.start,
+            self.rep2.end,
+            self.rep2.signal_value,
+            self.rep2.summit(),
+        )
+    }
+}
+
+/// holds the results of an IDR analysis, [NarrowPeakData::idr]
+pub struct IdrData {
+    model: IdrModel,
+    data: Vec<IdrRecord>,
+}
+
+impl IdrData {
+    /// the fitted copula mixture model
+    pub fn model(&self) -> &IdrModel {
+        &self.model
+    }
+
+    /// Writes the IDR results to stdout
+    pub fn print(&self) -> Result<(), Box<dyn Error>> {
+        let mut stdout = stdout();
+        for record in &self.data {
+            write!(stdout, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the IDR results to a file
+    pub fn to_file(&self, fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+        let outf = File::create(fname)?;
+        let mut writer = BufWriter::new(outf);
+
+        for record in &self.data {
+            write!(writer, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Returns number of records in self
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if self contains no records
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterate over the records in self
+    pub fn iter(&self) -> std::slice::Iter<'_, IdrRecord> {
+        self.data.iter()
+    }
+
+    /// returns the merged peaks with global IDR at or below the threshold
+    pub fn passing(&self, threshold: f64) -> NarrowPeakData {
+        let records: Vec<NarrowPeakRecord> = self.iter()
+            .filter(|x| x.global_idr <= threshold)
+            .map(|x| x.peak.clone())
+            .collect();
+        NarrowPeakData{data: records}
+    }
+}
+
+/// enables slicing of IdrData struct
+impl<Idx> std::ops::Index<Idx> for IdrData
+where
+    Idx: std::slice::SliceIndex<[IdrRecord]>,
+{
+    type Output = Idx::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+/// score cutoff used by [BEDGraphData::call_regions]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Threshold {
+    /// records with scores at or above this value pass
+    Absolute(f64),
+    /// records whose genome-wide robust z-score (see [BEDGraphData::robust_z])
+    /// is at or above this value pass
+    RobustZ(f64),
+}
+
+/// enriched region called by [BEDGraphData::call_regions]. Summaries
+/// are over the records passing the threshold within the region.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CalledRegion {
+    seqname: String,
+    start: usize,
+    end: usize,
+    summit: usize,
+    n_bins: usize,
+    max_score: f64,
+    mean_score: f64,
+    auc: f64,
+}
+
+impl CalledRegion {
+    /// summarizes the passing records making up a region, if there are any
+    fn from_members(members: &[&BEDGraphRecord]) -> Option<CalledRegion> {
+        let first = members.first()?;
+        let last = members.last()?;
+        let summit_rec = members.iter()
+            .fold(first, |best, x| if x.score > best.score { x } else { best });
+        let width: usize = members.iter().map(|x| x.width()).sum();
+        let auc: f64 = members.iter().map(|x| x.score * x.width() as f64).sum();
+        Some(CalledRegion {
+            seqname: first.seqname.to_string(),
+            start: first.start,
+            end: last.end,
+            summit: (summit_rec.start + summit_rec.end) / 2,
+            n_bins: members.len(),
+            max_score: summit_rec.score,
+            mean_score: auc / width as f64,
+            auc,
+        })
+    }
+
+    pub fn seqname(&self) -> &str {
+        &self.seqname
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// midpoint of the highest-scoring record in the region
+    pub fn summit(&self) -> usize {
+        self.summit
+    }
+
+    /// number of records passing the threshold in the region
+    pub fn n_bins(&self) -> usize {
+        self.n_bins
+    }
+
+    pub fn max_score(&self) -> f64 {
+        self.max_score
+    }
+
+    /// base-weighted mean score
+    pub fn mean_score(&self) -> f64 {
+        self.mean_score
+    }
+
+    /// area under the curve, the sum of each score multiplied by its record's width
+    pub fn auc(&self) -> f64 {
+        self.auc
+    }
+
+    /// converts self to a narrowPeak record, with the max score as the signal value,
+    /// the summit as the peak offset, and p- and q-values of -1
+    pub fn to_narrowpeak(&self, name: &str) -> NarrowPeakRecord {
+        NarrowPeakRecord::new(
+            self.seqname.to_string(),
+            self.start,
+            self.end,
+            name.to_string(),
+            0.0,
+            Strand::Unknown,
+            self.max_score,
+            -1.0,
+            -1.0,
+            (self.summit - self.start) as i64,
+        )
+    }
+}
+
+/// Implement `Display` for `CalledRegion`. Columns are seqname, start, end, summit,
+/// number of bins, max score, mean score, and area under the curve.
+impl fmt::Display for CalledRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.seqname,
+            self.start,
+            self.end,
+            self.summit,
+            self.n_bins,
+            self.max_score,
+            self.mean_score,
+            self.auc,
+        )
+    }
+}
+
+/// struct to define a single line of a union bedgraph file, holding
+/// one score per sample over a shared interval
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnionBEDGraphRecord {
+    seqname: String,
+    start: usize,
+    end: usize,
+    scores: Vec<f64>,
+}
+
+impl UnionBEDGraphRecord {
+    pub fn new(
+        seqname: String,
+        start: usize,
+        end: usize,
+        scores: Vec<f64>,
+    ) -> UnionBEDGraphRecord {
+        UnionBEDGraphRecord {
+            seqname,
+            start,
+            end,
+            scores,
+        }
+    }
+
+    pub fn seqname(&self) -> &str {
+        &self.seqname
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn scores(&self) -> &[f64] {
+        &self.scores
+    }
+}
+
+/// Implement `Display` for `UnionBEDGraphRecord`.
+impl fmt::Display for UnionBEDGraphRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\t{}\t{}", self.seqname, self.start, self.end)?;
+        for score in &self.scores {
+            write!(f, "\t{}", score)?;
+        }
+        writeln!(f)
+    }
+}
+
+/// holds a union bedgraph file, as written by `bedtools unionbedg`,
+/// with one score column per sample
+pub struct UnionBEDGraphData {
+    sample_names: Vec<String>,
+    data: Vec<UnionBEDGraphRecord>,
+}
+
+impl UnionBEDGraphData {
+
+    /// Read a union bedgraph file. If has_header is true, sample names are taken
+    /// from the fourth and later columns of the first line, as written by
+    /// `bedtools unionbedg -header`. Otherwise samples are named by their
+    /// one-based column order.
+    pub fn from_file(
+            fname: &path::PathBuf,
+            has_header: bool,
+    ) -> Result<UnionBEDGraphData, Box<dyn Error>> {
+
+        let file = File::open(fname).unwrap_or_else(|err| {
+            eprintln!("Problem reading union bedgraph file {:?}: {}", fname, err);
+            process::exit(1);
+        });
+        // open buffered reader to union bedgraph file
+        let buf_reader = BufReader::new(file);
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(has_header)
+            .from_reader(buf_reader);
+        let mut sample_names: Vec<String> = if has_header {
+            rdr.headers()?.iter().skip(3).map(String::from).collect()
+        } else {
+            Vec::new()
+        };
+        let mut records: Vec<UnionBEDGraphRecord> = Vec::new();
+
+        for result in rdr.records() {
+            let row = result.unwrap_or_else(|err| {
+                eprintln!("Problem with your union bedgraph records. Is {:?} a properly-formed union bedgraph file?: {}", fname, err);
+                process::exit(1);
+            });
+            let (seqname, start, end, fields) = row.deserialize::<(String, usize, usize, Vec<String>)>(None)?;
+            let scores = fields.iter()
+                .map(|x| parse_score(x, &MISSING_TOKENS))
+                .collect::<Result<Vec<f64>, _>>()?;
+            records.push(UnionBEDGraphRecord::new(seqname, start, end, scores));
+        }
+        if sample_names.is_empty() {
+            let n_samples = records.first().map_or(0, |x| x.scores.len());
+            sample_names = (1..=n_samples).map(|x| x.to_string()).collect();
+        }
+        Ok(UnionBEDGraphData{ sample_names, data: records })
+    }
+
+    /// Writes the union bedgraph data to a file, with a header line
+    /// of sample names if header is true
+    pub fn to_file(&self, fname: &path::PathBuf, header: bool) -> Result<(), Box<dyn Error>> {
+        let outf = File::create(fname)?;
+        let mut writer = BufWriter::new(outf);
+
+        if header {
+            writeln!(writer, "chrom\tstart\tend\t{}", self.sample_names.join("\t"))?;
+        }
+        for record in &self.data {
+            write!(writer, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the union bedgraph data to stdout
+    pub fn print(&self) -> Result<(), Box<dyn Error>> {
+        let mut stdout = stdout();
+        for record in &self.data {
+            write!(stdout, "{}", record)?;
+        }
+        Ok(())
+    }
+
+    /// Returns number of records in self
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if self contains no records
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn sample_names(&self) -> &[String] {
+        &self.sample_names
+    }
+
+    /// Iterate over the records in self
+    pub fn iter(&self) -> std::slice::Iter<'_, UnionBEDGraphRecord> {
+        self.data.iter()
+    }
+
+    /// returns the scores of the sample at column i as a [BEDGraphData]
+    pub fn get_track(&self, i: usize) -> Result<BEDGraphData, Box<dyn Error>> {
+        if i >= self.sample_names.len() {
+            return Err(Box::new(InvalidParameter {
+                message: format!(
+                    "sample index {} is out of range for {} samples",
+                    i,
+                    self.sample_names.len(),
+                ),
+            }));
+        }
+        let records: Vec<BEDGraphRecord> = self.iter()
+            .map(|x| BEDGraphRecord::new(x.seqname.to_string(), x.start, x.end, x.scores[i]))
+            .collect();
+        Ok(BEDGraphData{data: records})
+    }
+}
+
+/// enables slicing of UnionBEDGraphData struct
+impl<Idx> std::ops::Index<Idx> for UnionBEDGraphData
+where
+    Idx: std::slice::SliceIndex<[UnionBEDGraphRecord]>,
+{
+    type Output = Idx::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+/// splits the intervals covered by any of tracks at every breakpoint in any track,
+/// returning each interval with the score of each track over it, or None for
+/// tracks without a record over the interval
+fn union_intervals(
+        tracks: &[&BEDGraphData],
+) -> Vec<(String, usize, usize, Vec<Option<f64>>)> {
+    let indices: Vec<BEDGraphIndex> = tracks.iter().map(|x| BEDGraphIndex::new(x)).collect();
+    let contigs: Vec<String> = tracks.iter()
+        .flat_map(|x| x.get_contigs())
+        .unique()
+        .collect();
+    let mut intervals = Vec::new();
+
+    for contig in contigs {
+        let ctg_records: Vec<Vec<&BEDGraphRecord>> = indices.iter()
+            .map(|index| index.overlaps(&contig, 0, usize::MAX).map(|x| x.0).collect())
+            .collect();
+        let breakpoints: Vec<usize> = ctg_records.iter()
+            .flatten()
+            .flat_map(|x| [x.start, x.end])
+            .sorted()
+            .dedup()
+            .collect();
+        let mut cursors: Vec<usize> = vec![0; tracks.len()];
+
+        for bounds in breakpoints.windows(2) {
+            let (start, end) = (bounds[0], bounds[1]);
+            let mut scores: Vec<Option<f64>> = Vec::with_capacity(tracks.len());
+            for (track_records, cursor) in ctg_records.iter().zip(cursors.iter_mut()) {
+                while *cursor < track_records.len() && track_records[*cursor].end <= start {
+                    *cursor += 1;
+                }
+                match track_records.get(*cursor) {
+                    Some(record) if record.start <= start => scores.push(Some(record.score)),
+                    _ => scores.push(None),
+                }
             }
-            // Handle the last record if it's the final one and not full-width
-            if is_final_record && width % w != 0 {
-                records.push(BEDGraphRecord {
-                    seqname: self.seqname.to_string(),
-                    start: self.start + num_full_width_records * w,
-                    end: self.end,
-                    score: self.score,
-                });
+            if scores.iter().any(|x| x.is_some()) {
+                intervals.push((contig.to_string(), start, end, scores));
             }
-            Ok(records)
+        }
+    }
+    intervals
+}
+
+/// combines several bedgraph tracks, which may have different breakpoints, into a
+/// [UnionBEDGraphData] with one score column per track, in the manner of
+/// `bedtools unionbedg`. Intervals are split at every breakpoint in any track,
+/// tracks without a record over an interval are given the score fill, and intervals
+/// covered by no track are omitted.
+pub fn union_bedgraph(
+        tracks: &[&BEDGraphData],
+        names: &[&str],
+        fill: f64,
+) -> Result<UnionBEDGraphData, Box<dyn Error>> {
+    if tracks.len() != names.len() {
+        return Err(Box::new(InvalidParameter {
+            message: format!(
+                "{} sample names were given for {} tracks",
+                names.len(),
+                tracks.len(),
+            ),
+        }));
+    }
+    let records: Vec<UnionBEDGraphRecord> = union_intervals(tracks).into_iter()
+        .map(|(seqname, start, end, scores)| {
+            let scores = scores.iter().map(|x| x.unwrap_or(fill)).collect();
+            UnionBEDGraphRecord::new(seqname, start, end, scores)
+        }).collect();
+    let sample_names = names.iter().map(|x| x.to_string()).collect();
+    Ok(UnionBEDGraphData{ sample_names, data: records })
+}
+
+/// returns the scores of each track quantile normalized across tracks, so that every
+/// track has the same score distribution. Tracks must have equal numbers of records,
+/// and are typically on a common bin grid, e.g. from [BEDGraphData::rebin].
+/// Tied scores within a track all receive the mean of the values at their ranks.
+///
+/// Under [NanPolicy::Propagate], any missing score makes every normalized score NaN.
+/// Under [NanPolicy::Skip], missing scores remain missing, and each track's present
+/// scores are spread evenly over the ranks of the reference distribution, interpolating
+/// linearly between them, so that tracks with no missing scores are normalized as usual.
+pub fn get_quantile_normalized(
+        tracks: &[&BEDGraphData],
+        nan_policy: NanPolicy,
+) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let n_records = tracks.first().map_or(0, |x| x.len());
+    if tracks.iter().any(|x| x.len() != n_records) {
+        return Err(Box::new(InvalidParameter {
+            message: String::from("quantile normalization requires tracks with equal numbers of records"),
+        }));
+    }
+    let scores: Vec<Vec<f64>> = tracks.iter()
+        .map(|x| x.fetch_scores())
+        .collect::<Result<_, _>>()?;
+    if nan_policy == NanPolicy::Propagate && scores.iter().flatten().any(|x| x.is_nan()) {
+        return Ok(vec![vec![f64::NAN; n_records]; tracks.len()]);
+    }
+    // indices of each track's present scores, in ascending order of score
+    let orders: Vec<Vec<usize>> = scores.iter()
+        .map(|track_scores| {
+            (0..n_records)
+                .filter(|i| !track_scores[*i].is_nan())
+                .sorted_by(|a, b| track_scores[*a].total_cmp(&track_scores[*b]))
+                .collect()
+        }).collect();
+    // value at rank of n ranks, placed on the same relative position among the
+    // values, interpolating between neighbours. The position is computed from
+    // integers so that it is exact when there are n values.
+    let at_rank = |values: &[f64], rank: usize, n: usize| -> f64 {
+        let pos = if n > 1 {
+            (rank * (values.len() - 1)) as f64 / (n - 1) as f64
         } else {
-            // Return an error if the width is not divisible by 'w'.
-            Err(BEDGraphRecordNotDivisible{
-                start: self.start,
-                end: self.end,
-                width: w,
-            })
+            (values.len() - 1) as f64 / 2.0
+        };
+        let lo = pos.floor() as usize;
+        let hi = pos.ceil() as usize;
+        values[lo] + (values[hi] - values[lo]) * (pos - lo as f64)
+    };
+    let sorted: Vec<Vec<f64>> = orders.iter()
+        .zip(scores.iter())
+        .filter(|(order, _)| !order.is_empty())
+        .map(|(order, track_scores)| order.iter().map(|i| track_scores[*i]).collect())
+        .collect();
+    let rank_means: Vec<f64> = (0..n_records)
+        .map(|rank| {
+            let total: f64 = sorted.iter()
+                .map(|values| at_rank(values, rank, n_records))
+                .sum();
+            total / sorted.len() as f64
+        }).collect();
+
+    let mut normalized: Vec<Vec<f64>> = Vec::with_capacity(tracks.len());
+    for (order, track_scores) in orders.iter().zip(scores.iter()) {
+        let mut track_norm = vec![f64::NAN; n_records];
+        let n_present = order.len();
+        let mut rank = 0;
+        while rank < n_present {
+            let mut tie_end = rank + 1;
+            while tie_end < n_present && track_scores[order[tie_end]] == track_scores[order[rank]] {
+                tie_end += 1;
+            }
+            let tie_mean = (rank..tie_end)
+                .map(|r| at_rank(&rank_means, r, n_present))
+                .sum::<f64>() / (tie_end - rank) as f64;
+            for idx in &order[rank..tie_end] {
+                track_norm[*idx] = tie_mean;
+            }
+            rank = tie_end;
+        }
+        normalized.push(track_norm);
+    }
+    Ok(normalized)
+}
+
+/// quantile normalizes the scores of each track in place. See [get_quantile_normalized].
+pub fn to_quantile_normalized(
+        tracks: &mut [&mut BEDGraphData],
+        nan_policy: NanPolicy,
+) -> Result<(), Box<dyn Error>> {
+    let normalized = {
+        let views: Vec<&BEDGraphData> = tracks.iter().map(|x| &**x).collect();
+        get_quantile_normalized(&views, nan_policy)?
+    };
+    for (track, scores) in tracks.iter_mut().zip(normalized.iter()) {
+        track.set_scores(scores);
+    }
+    Ok(())
+}
+
+/// correlation coefficient computed by [correlation_matrix]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Correlation {
+    Pearson,
+    /// Pearson correlation of the ranks, with tied scores given their average rank
+    Spearman,
+}
+
+impl Correlation {
+    /// correlation between paired values, or NaN if either has no variance
+    pub fn coefficient(&self, x: &[f64], y: &[f64]) -> f64 {
+        match self {
+            Correlation::Pearson => pearson(x, y),
+            Correlation::Spearman => pearson(&average_ranks(x), &average_ranks(y)),
+        }
+    }
+}
+
+/// Pearson correlation between paired values, or NaN if either has no variance
+fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let mut cov = 0.0;
+    let mut var_x = 0.0;
+    let mut var_y = 0.0;
+    for (a,b) in x.iter().zip(y.iter()) {
+        cov += (a - mean_x) * (b - mean_y);
+        var_x += (a - mean_x).powi(2);
+        var_y += (b - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        f64::NAN
+    } else {
+        cov / (var_x * var_y).sqrt()
+    }
+}
+
+/// restricts the intervals over which [correlation_matrix] compares tracks
+#[derive(Clone, Copy, Default)]
+pub struct CorrelationFilter<'a> {
+    /// if given, only intervals overlapping these regions are used
+    pub regions: Option<&'a BEDData>,
+    /// if given, intervals overlapping these regions are excluded
+    pub blacklist: Option<&'a BEDData>,
+    /// if true, intervals where both tracks of a pair score zero are excluded
+    pub skip_zeros: bool,
+}
+
+/// returns the matrix of pairwise correlations between tracks, with rows labelled by
+/// names. Tracks are compared over the common grid of intervals from splitting them
+/// at every breakpoint in any track, as in [union_bedgraph], so tracks on the same
+/// bin grid are compared bin by bin. Each pair uses the intervals that both tracks
+/// cover and that pass the filter, and pairs with fewer than two such intervals
+/// or with no variance are given NaN. The diagonal is found likewise, so it is NaN
+/// for tracks with fewer than two such intervals or with no variance, and 1 otherwise.
+pub fn correlation_matrix(
+        tracks: &[&BEDGraphData],
+        names: &[&str],
+        method: Correlation,
+        filter: &CorrelationFilter,
+) -> Result<SignalMatrix, Box<dyn Error>> {
+    if tracks.len() != names.len() {
+        return Err(Box::new(InvalidParameter {
+            message: format!(
+                "{} sample names were given for {} tracks",
+                names.len(),
+                tracks.len(),
+            ),
+        }));
+    }
+    let regions = filter.regions.map(|x| x.merged_intervals());
+    let blacklist = filter.blacklist.map(|x| x.merged_intervals());
+    let passes = |seqname: &str, start: usize, end: usize| -> bool {
+        let in_regions = match &regions {
+            Some(r) => r.get(seqname).is_some_and(|x| overlaps_merged(x, start, end)),
+            None => true,
+        };
+        let in_blacklist = match &blacklist {
+            Some(b) => b.get(seqname).is_some_and(|x| overlaps_merged(x, start, end)),
+            None => false,
+        };
+        in_regions && !in_blacklist
+    };
+    let intervals: Vec<Vec<Option<f64>>> = union_intervals(tracks).into_iter()
+        .filter(|(seqname, start, end, _)| passes(seqname, *start, *end))
+        .map(|x| x.3)
+        .collect();
+
+    let n = tracks.len();
+    let mut values = vec![f64::NAN; n * n];
+    for i in 0..n {
+        for j in i..n {
+            let (x, y): (Vec<f64>, Vec<f64>) = intervals.iter()
+                .filter_map(|scores| match (scores[i], scores[j]) {
+                    (Some(a), Some(b)) => Some((a, b)),
+                    _ => None,
+                })
+                .filter(|(a, b)| !(filter.skip_zeros && *a == 0.0 && *b == 0.0))
+                .unzip();
+            let mut r = if x.len() < 2 { f64::NAN } else { method.coefficient(&x, &y) };
+            // a track correlates perfectly with itself, up to rounding
+            if i == j && !r.is_nan() {
+                r = 1.0;
+            }
+            values[i * n + j] = r;
+            values[j * n + i] = r;
+        }
+    }
+    Ok(SignalMatrix {
+        row_labels: names.iter().map(|x| x.to_string()).collect(),
+        values,
+        ncol: n,
+    })
+}
+
+/// arithmetic operation between the scores of two tracks,
+/// as used by [BEDGraphData::compare]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TrackOp {
+    Add,
+    Subtract,
+    Multiply,
+    Ratio,
+    /// log2((self + pseudocount) / (other + pseudocount))
+    Log2Ratio {
+        pseudocount: f64,
+    },
+}
+
+/// how [BEDGraphData::combine] handles infinite or NaN results from two finite scores
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NonFinitePolicy {
+    /// the result is written as it is, e.g. inf for a ratio with a zero denominator
+    Keep,
+    /// the result is written as NaN, i.e. as a missing score
+    Nan,
+    /// the whole operation fails with an error naming the first such record
+    Error,
+}
+
+impl TrackOp {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            TrackOp::Add => a + b,
+            TrackOp::Subtract => a - b,
+            TrackOp::Multiply => a * b,
+            TrackOp::Ratio => a / b,
+            TrackOp::Log2Ratio { pseudocount } => ((a + pseudocount) / (b + pseudocount)).log2(),
+        }
+    }
+}
+
+/// kind of z-score calculated by [BEDGraphData::z_score]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ZScore {
+    /// (score - median) / sigma, where sigma is the given dispersion
+    /// estimator scaled by [Dispersion::sigma]
+    Robust(Dispersion),
+    /// (score - mean) / SD
+    Standard,
+}
+
+impl ZScore {
+    /// returns the center and spread of scores used to calculate this kind of z-score,
+    /// handling missing scores as given by nan_policy
+    fn center_and_spread(
+            &self,
+            scores: &[f64],
+            nan_policy: NanPolicy,
+    ) -> Result<(f64, f64), Box<dyn Error>> {
+        let mut scores = match nan_policy.select(scores) {
+            Some(scores) => scores,
+            None => return Ok((f64::NAN, f64::NAN)),
+        };
+        let scores = &mut scores[..];
+        match self {
+            ZScore::Robust(dispersion) => {
+                let sigma = dispersion.sigma(scores)?;
+                let score_median = median(scores)?;
+                Ok((score_median, sigma))
+            },
+            ZScore::Standard => Ok((mean(scores)?, std_dev(scores)?)),
+        }
+    }
+
+    fn apply(&self, x: f64, center: f64, spread: f64) -> f64 {
+        if spread == 0.0 {
+            return f64::NAN;
+        }
+        match self {
+            ZScore::Robust(_) => robust_z(x, center, spread),
+            ZScore::Standard => (x - center) / spread,
+        }
+    }
+}
+
+/// groups of records within which [BEDGraphData::z_score] computes
+/// the center and spread of the scores
+#[derive(Clone, Copy)]
+pub enum ZScope<'a> {
+    /// a single group of all records
+    Genome,
+    /// one group per contig, for genomes whose replicons differ in copy number
+    Contig,
+    /// one group per region, of the records overlapping that region. Records overlapping
+    /// several regions are grouped with the first, and records overlapping none are given NaN.
+    Regions(&'a BEDData),
+    /// a window of this many base pairs centered on the midpoint of each record, holding
+    /// the records whose midpoints lie within it, as in [BEDGraphData::roll_fn_bp], and
+    /// truncated at contig ends. Windows of even and odd widths alike are centered exactly,
+    /// as windows are half-open. The center and spread are updated as windows slide,
+    /// except for robust z-scores using the MAD, Qn or Sn, which are found for each
+    /// window in turn.
+    Window(usize),
+}
+
+/// Sorted per-contig index into the records of a [BEDGraphData], allowing
+/// the records overlapping a region to be found by binary search.
+/// Records within a contig are assumed not to overlap one another.
+struct BEDGraphIndex<'a> {
+    bgd: &'a BEDGraphData,
+    contigs: HashMap<String, Vec<usize>>,
+}
+
+impl<'a> BEDGraphIndex<'a> {
+    fn new(bgd: &'a BEDGraphData) -> BEDGraphIndex<'a> {
+        let mut contigs: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i,record) in bgd.data.iter().enumerate() {
+            contigs.entry(record.seqname.to_string()).or_default().push(i);
+        }
+        for idxs in contigs.values_mut() {
+            idxs.sort_by_key(|i| bgd.data[*i].start);
+        }
+        BEDGraphIndex { bgd, contigs }
+    }
+
+    /// iterates over the positions in the indexed [BEDGraphData] of the records
+    /// overlapping [start, end) on seqname, in order
+    fn overlap_indices(
+            &self,
+            seqname: &str,
+            start: usize,
+            end: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let idxs: &[usize] = self.contigs.get(seqname).map_or(&[], |x| x.as_slice());
+        let data = &self.bgd.data;
+        let first = idxs.partition_point(|i| data[*i].end <= start);
+        idxs[first..].iter()
+            .copied()
+            .take_while(move |i| data[*i].start < end)
+    }
+
+    /// iterates over the records overlapping [start, end) on seqname, in order,
+    /// yielding each record with the number of base pairs by which it overlaps
+    fn overlaps(
+            &self,
+            seqname: &str,
+            start: usize,
+            end: usize,
+    ) -> impl Iterator<Item = (&'a BEDGraphRecord, usize)> + '_ {
+        let data = &self.bgd.data;
+        self.overlap_indices(seqname, start, end)
+            .map(move |i| &data[i])
+            .map(move |x| (x, x.end.min(end) - x.start.max(start)))
+    }
+}
+
+/// summary of bedgraph signal over a single region, as produced
+/// by [BEDGraphData::region_summary]
+#[derive(Debug, PartialEq, Clone)]
+pub struct RegionSummary {
+    region: BEDRecord,
+    covered: usize,
+    sum: f64,
+    mean_covered: f64,
+    mean_region: f64,
+    min: f64,
+    max: f64,
+    median: f64,
+}
+
+impl RegionSummary {
+    /// builds the summary from (score, overlapping bp) pairs
+    fn from_pairs(region: &BEDRecord, pairs: &mut [(f64, usize)]) -> RegionSummary {
+        let covered: usize = pairs.iter().map(|x| x.1).sum();
+        let sum: f64 = pairs.iter().map(|x| x.0 * x.1 as f64).sum();
+        let width = region.end - region.start;
+        let min = pairs.iter().map(|x| x.0).fold(f64::NAN, f64::min);
+        let max = pairs.iter().map(|x| x.0).fold(f64::NAN, f64::max);
+        RegionSummary {
+            region: region.clone(),
+            covered,
+            sum,
+            mean_covered: sum / covered as f64,
+            mean_region: if width > 0 { sum / width as f64 } else { f64::NAN },
+            min,
+            max,
+            median: weighted_median(pairs),
+        }
+    }
+
+    /// sets every statistic, but not the coverage, to NaN
+    fn propagate_nan(&mut self) {
+        self.sum = f64::NAN;
+        self.mean_covered = f64::NAN;
+        self.mean_region = f64::NAN;
+        self.min = f64::NAN;
+        self.max = f64::NAN;
+        self.median = f64::NAN;
+    }
+
+    pub fn region(&self) -> &BEDRecord {
+        &self.region
+    }
+
+    /// number of bases in the region covered by bedgraph records
+    pub fn covered(&self) -> usize {
+        self.covered
+    }
+
+    /// sum of scores over covered bases
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// mean score over covered bases, NaN if no bases are covered
+    pub fn mean_covered(&self) -> f64 {
+        self.mean_covered
+    }
+
+    /// mean score over the whole region, counting uncovered bases as zero
+    pub fn mean_region(&self) -> f64 {
+        self.mean_region
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// base-weighted median score over covered bases
+    pub fn median(&self) -> f64 {
+        self.median
+    }
+
+    /// fraction of the region's bases covered by bedgraph records, NaN for a zero-width region
+    pub fn fraction_covered(&self) -> f64 {
+        let width = self.region.end.saturating_sub(self.region.start);
+        if width == 0 {
+            return f64::NAN;
         }
+        self.covered as f64 / width as f64
     }
 }
 
-/// Implement `Display` for `BEDRecord`.
-impl fmt::Display for BEDRecord {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}\t{}\t{}\n", self.seqname, self.start, self.end)
+/// how [BEDGraphData::histogram] places the edges of its bins
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HistogramBins {
+    /// this many bins of equal width between the minimum and maximum scores
+    Fixed(usize),
+    /// this many bins, each holding about the same weight of scores. Bins whose
+    /// edges coincide because of tied scores are merged.
+    Quantile(usize),
+    /// bins of equal width 2 * IQR / n^(1/3), as proposed by Freedman and Diaconis
+    /// (1981), where n is the total weight of the scores. There are never more bins
+    /// than records, so that a tiny IQR with outliers cannot give a huge number of
+    /// bins, and Sturges' rule, ceil(log2(n)) + 1 bins, is used if the IQR is zero.
+    /// A single bin is used if all scores are equal.
+    FreedmanDiaconis,
+}
+
+/// histogram of scores, as returned by [BEDGraphData::histogram]. Each bin includes
+/// its lower edge and excludes its upper edge, except the last, which includes both.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Histogram {
+    edges: Vec<f64>,
+    counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// builds a histogram of the sorted (score, weight) pairs over the given edges
+    fn from_sorted(sorted: &[(f64, usize)], edges: Vec<f64>) -> Histogram {
+        let n_bins = edges.len() - 1;
+        let mut counts = vec![0; n_bins];
+        for (score, weight) in sorted {
+            let bin = edges.partition_point(|x| x <= score)
+                .saturating_sub(1)
+                .min(n_bins - 1);
+            counts[bin] += weight;
+        }
+        Histogram{edges, counts}
+    }
+
+    /// the n_bins + 1 bin edges, in increasing order
+    pub fn edges(&self) -> &[f64] {
+        &self.edges
+    }
+
+    /// the number of records, or base pairs if weighted, in each bin
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    pub fn n_bins(&self) -> usize {
+        self.counts.len()
     }
 }
 
-/// Implement `Display` for `BEDGraphRecord`.
-impl fmt::Display for BEDGraphRecord {
+/// Implement `Display` for `Histogram`, with one line of lower edge,
+/// upper edge, and count per bin
+impl fmt::Display for Histogram {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}\t{}\t{}\t{}\n", self.seqname, self.start, self.end, self.score)
+        for (i,count) in self.counts.iter().enumerate() {
+            writeln!(f, "{}\t{}\t{}", self.edges[i], self.edges[i + 1], count)?;
+        }
+        Ok(())
     }
 }
 
-/// holds a bed file
-pub struct BEDData {
-    data: Vec<BEDRecord>,
+/// empirical cumulative distribution function of scores, as returned by
+/// [BEDGraphData::ecdf], which maps scores to percentiles and back
+#[derive(Debug, PartialEq, Clone)]
+pub struct EmpiricalCdf {
+    values: Vec<f64>,
+    cumulative: Vec<f64>,
 }
 
-/// holds a bedgraph file
-pub struct BEDGraphData {
-    data: Vec<BEDGraphRecord>,
-}
+impl EmpiricalCdf {
+    /// builds the function from sorted (score, weight) pairs with nonzero total weight
+    fn from_sorted(sorted: &[(f64, usize)]) -> EmpiricalCdf {
+        let total: usize = sorted.iter().map(|x| x.1).sum();
+        let mut values: Vec<f64> = Vec::new();
+        let mut cumulative: Vec<f64> = Vec::new();
+        let mut running = 0;
+        for (score, weight) in sorted {
+            running += weight;
+            let fraction = running as f64 / total as f64;
+            if values.last() == Some(score) {
+                *cumulative.last_mut().unwrap() = fraction;
+            } else {
+                values.push(*score);
+                cumulative.push(fraction);
+            }
+        }
+        EmpiricalCdf{values, cumulative}
+    }
 
-impl BEDGraphData {
+    /// fraction of the scores, or of the base pairs if weighted, at or below score
+    pub fn cdf(&self, score: f64) -> f64 {
+        let i = self.values.partition_point(|x| *x <= score);
+        if i == 0 { 0.0 } else { self.cumulative[i - 1] }
+    }
 
-    pub fn from_record_vec(rec_vec: Vec<BEDGraphRecord>) -> BEDGraphData {
-        BEDGraphData{ data: rec_vec }
+    /// the smallest score at which the cdf is at least p, for p in [0, 1]
+    pub fn quantile(&self, p: f64) -> Result<f64, Box<dyn Error>> {
+        check_probabilities(&[p])?;
+        let i = self.cumulative.partition_point(|x| *x < p);
+        Ok(self.values[i.min(self.values.len() - 1)])
     }
+}
 
-    /// Parse bedgraph records from stdin
-    pub fn from_stdin() -> Result<BEDGraphData, Box<dyn Error>> {
-        let mut rdr = csv::ReaderBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_reader(stdin());
-        let mut records: Vec<BEDGraphRecord> = Vec::new();
+/// summary statistics of the scores of a set of bedgraph records,
+/// as returned by [BEDGraphData::summary]
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScoreSummary {
+    label: String,
+    count: usize,
+    nan_count: usize,
+    covered: usize,
+    mean: f64,
+    sd: f64,
+    min: f64,
+    max: f64,
+    quantiles: Vec<(f64, f64)>,
+}
 
-        for result in rdr.deserialize() {
-            let record = result.unwrap_or_else(|err| {
-                eprintln!("Problem with your bedgraph records. Is the stdin a properly-formed bedgraph file?: {}", err);
-                process::exit(1);
-            });
-            records.push(record);
+impl ScoreSummary {
+    /// summarizes records, excluding those with NaN scores from all but nan_count
+    fn from_records(
+            label: &str,
+            records: &[&BEDGraphRecord],
+            quantiles: &[f64],
+            bp_weighted: bool,
+    ) -> ScoreSummary {
+        let mut pairs: Vec<(f64, usize)> = records.iter()
+            .filter(|x| !x.score.is_nan())
+            .map(|x| (x.score, if bp_weighted { x.width() } else { 1 }))
+            .collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let weight: usize = pairs.iter().map(|x| x.1).sum();
+        let mean = pairs.iter().map(|x| x.0 * x.1 as f64).sum::<f64>() / weight as f64;
+        let ss: f64 = pairs.iter().map(|x| (x.0 - mean).powi(2) * x.1 as f64).sum();
+        ScoreSummary {
+            label: label.to_string(),
+            count: pairs.len(),
+            nan_count: records.len() - pairs.len(),
+            covered: records.iter()
+                .filter(|x| !x.score.is_nan())
+                .map(|x| x.width())
+                .sum(),
+            mean,
+            sd: (ss / (weight as f64 - 1.0)).sqrt(),
+            min: pairs.first().map_or(f64::NAN, |x| x.0),
+            max: pairs.last().map_or(f64::NAN, |x| x.0),
+            quantiles: quantiles.iter()
+                .map(|p| (*p, weighted_quantile_sorted(&pairs, *p)))
+                .collect(),
         }
-        Ok(BEDGraphData{ data: records })
     }
 
-    /// Parse bedgraph records from stdin, printing contiguous regions as bed format
-    pub fn print_contiguous_regions_from_stdin() -> Result<(), Box<dyn Error>> {
-        // read from stdin
-        let mut rdr = csv::ReaderBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_reader(stdin());
-        // establish connection to stdout
-        let mut stdout = stdout();
+    /// name of the contig summarized, or "genome" for all contigs
+    pub fn label(&self) -> &str {
+        &self.label
+    }
 
-        let mut region = BEDRecord::new("".into(), 0, 0);
-        let mut first_row = true;
+    /// number of records with scores other than NaN
+    pub fn count(&self) -> usize {
+        self.count
+    }
 
-        for result in rdr.deserialize() {
-            let record: BEDGraphRecord = result.unwrap_or_else(|err| {
-                eprintln!("Problem with your bedgraph records. Is the stdin a properly-formed bedgraph file?: {}", err);
-                process::exit(1);
-            });
-            if region.seqname != record.seqname {
-                // if we've hit a new seqname and this is not the first row, then print bed record
-                // prior to setting new region
-                if !first_row {
-                    write!(stdout, "{}", region)?;
-                }
-                region.set(&record.seqname, &record.start, &record.end);
-            } else {
-                // if we're in the same seqname, check whether region's end and record's start are
-                // equal. If they are equal, region is contiguous with record, so set region end to
-                // record end
-                if region.end == record.start {
-                    region.set_end(&record.end);
-                // if they're not equal, these are not contiguous, so print the region and re-set
-                // region to have this record's attributes
-                } else {
-                    write!(stdout, "{}", region)?;
-                    region.set_start(&record.start);
-                    region.set_end(&record.end);
-                }
-            }
-            first_row = false;
-        }
-        // write the final region if it is different than beginning region
-        if !(region.seqname == "") {
-            write!(stdout, "{}", region)?;
+    /// number of records with NaN scores
+    pub fn nan_count(&self) -> usize {
+        self.nan_count
+    }
+
+    /// number of base pairs covered by records with scores other than NaN
+    pub fn covered(&self) -> usize {
+        self.covered
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// sample standard deviation
+    pub fn sd(&self) -> f64 {
+        self.sd
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// (probability, quantile) pairs, in the order requested
+    pub fn quantiles(&self) -> &[(f64, f64)] {
+        &self.quantiles
+    }
+}
+
+/// Implement `Display` for `ScoreSummary`, with one column per quantile
+impl fmt::Display for ScoreSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.label,
+            self.count,
+            self.nan_count,
+            self.covered,
+            self.mean,
+            self.sd,
+            self.min,
+            self.max,
+        )?;
+        for (_, value) in &self.quantiles {
+            write!(f, "\t{}", value)?;
         }
-        Ok(())
+        writeln!(f)
     }
+}
 
-    /// Read begraph file line-by-line, printing each contigous regions to stdout as they are
-    /// identified
-    pub fn print_contiguous_regions(fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+/// Implement `Display` for `RegionSummary`. Columns are seqname, start, end, name,
+/// size, covered, sum, mean over region, mean over covered bases, min, max,
+/// median, and fraction covered.
+impl fmt::Display for RegionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.region.seqname,
+            self.region.start,
+            self.region.end,
+            self.region.name.as_deref().unwrap_or("."),
+            self.region.end - self.region.start,
+            self.covered,
+            self.sum,
+            self.mean_region,
+            self.mean_covered,
+            self.min,
+            self.max,
+            self.median,
+            self.fraction_covered(),
+        )
+    }
+}
 
-        let file = File::open(fname).unwrap_or_else(|err| {
-            eprintln!("Problem reading bedgraph file {:?}: {}", fname, err);
-            process::exit(1);
-        });
-        // open buffered reader to bedgraph file
-        let buf_reader = BufReader::new(file);
-        // establish connection to stdout
-        let mut stdout = stdout();
+/// a row of a GFF3 or GTF file, before its attributes are parsed
+#[derive(Debug, Deserialize)]
+struct GffRow {
+    seqname: String,
+    source: String,
+    feature_type: String,
+    start: usize,
+    end: usize,
+    #[serde(deserialize_with = "deserialize_optional_score")]
+    score: Option<f64>,
+    strand: Strand,
+    phase: String,
+    attributes: String,
+}
 
-        let mut rdr = csv::ReaderBuilder::new()
-            .delimiter(b'\t')
-            .has_headers(false)
-            .from_reader(buf_reader);
+/// format of a GFF file, which determines how its attribute column is written
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum GffFormat {
+    /// key=value pairs, with reserved characters percent-encoded
+    Gff3,
+    /// key "value" pairs
+    Gtf,
+}
 
-        let mut region = BEDRecord::new("".into(), 0, 0);
-        let mut first_row = true;
+impl GffFormat {
+    /// guesses the format from an attribute column, by whether the key of its first
+    /// pair ends at an equals sign or a space
+    fn detect(attributes: &str) -> GffFormat {
+        let first = attributes.trim_start();
+        match (first.find('='), first.find(' ')) {
+            (Some(eq), Some(space)) if space < eq => GffFormat::Gtf,
+            (None, Some(_)) => GffFormat::Gtf,
+            _ => GffFormat::Gff3,
+        }
+    }
+}
 
-        for result in rdr.deserialize() {
-            let record: BEDGraphRecord = result.unwrap_or_else(|err| {
-                eprintln!("Problem with your bedgraph records. Is {:?} a properly-formed bedgraph file?: {}", fname, err);
-                process::exit(1);
-            });
-            if region.seqname != record.seqname {
-                // if we've hit a new seqname and this is not the first row, then print bed record
-                // prior to setting new region
-                if !first_row {
-                    write!(stdout, "{}", region)?;
-                }
-                region.set(&record.seqname, &record.start, &record.end);
-            } else {
-                // if we're in the same seqname, check whether region's end and record's start are
-                // equal. If they are equal, region is contiguous with record, so set region end to
-                // record end
-                if region.end == record.start {
-                    region.set_end(&record.end);
-                // if they're not equal, these are not contiguous, so print the region and re-set
-                // region to have this record's attributes
-                } else {
-                    write!(stdout, "{}", region)?;
-                    region.set_start(&record.start);
-                    region.set_end(&record.end);
-                }
-            }
-            first_row = false;
+/// decodes %XX escapes, as used for reserved characters in GFF3 attributes.
+/// Malformed escapes are kept as they are.
+fn percent_decode(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|x| std::str::from_utf8(x).ok())
+            .and_then(|x| u8::from_str_radix(x, 16).ok());
+        match hex {
+            Some(byte) if bytes[i] == b'%' => {
+                decoded.push(byte);
+                i += 3;
+            },
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
         }
-        // write the final region if it is different than beginning region
-        if !(region.seqname == "") {
-            write!(stdout, "{}", region)?;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// splits a GTF attribute column at the semicolons outside double-quoted values
+fn split_gtf_attributes(field: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut quoted = false;
+    let mut part_start = 0;
+    for (i,c) in field.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&field[part_start..i]);
+                part_start = i + 1;
+            },
+            _ => {},
         }
+    }
+    parts.push(&field[part_start..]);
+    parts
+}
 
-        Ok(())
+/// parses a GFF3 attribute column of key=value pairs, or a GTF attribute
+/// column of key "value" pairs, each separated by semicolons. GFF3 values
+/// escape their semicolons, while GTF values may hold them within quotes.
+fn parse_gff_attributes(field: &str, format: GffFormat) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let parts = match format {
+        GffFormat::Gff3 => field.split(';').collect(),
+        GffFormat::Gtf => split_gtf_attributes(field),
+    };
+    for part in parts.iter().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        match format {
+            GffFormat::Gff3 => if let Some((key, value)) = part.split_once('=') {
+                attributes.insert(percent_decode(key.trim()), percent_decode(value.trim()));
+            },
+            GffFormat::Gtf => if let Some((key, value)) = part.split_once(' ') {
+                attributes.insert(key.trim().to_string(), value.trim().trim_matches('"').to_string());
+            },
+        }
     }
+    attributes
+}
 
-    /// Read a bedgraph file
-    pub fn from_file(fname: &path::PathBuf) -> Result<BEDGraphData, Box<dyn Error>> {
+/// struct to define a single feature of a GFF3 or GTF file. Coordinates are
+/// converted from one-based, closed intervals to zero-based, half-open intervals,
+/// as in bed files.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GffRecord {
+    seqname: String,
+    source: String,
+    feature_type: String,
+    start: usize,
+    end: usize,
+    score: Option<f64>,
+    strand: Strand,
+    phase: Option<u8>,
+    attributes: HashMap<String, String>,
+}
+
+impl GffRecord {
+    pub fn seqname(&self) -> &str {
+        &self.seqname
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// the feature type, e.g. gene, mRNA, exon, or CDS
+    pub fn feature_type(&self) -> &str {
+        &self.feature_type
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+
+    pub fn strand(&self) -> Strand {
+        self.strand
+    }
+
+    pub fn phase(&self) -> Option<u8> {
+        self.phase
+    }
+
+    /// the value of an attribute, such as ID, Parent, or gene_id
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(|x| x.as_str())
+    }
+}
+
+/// feature types treated as genes when building [GeneModel]s
+const GENE_TYPES: [&str; 2] = ["gene", "pseudogene"];
+
+/// holds a GFF3 or GTF file
+pub struct GffData {
+    data: Vec<GffRecord>,
+}
+
+impl GffData {
+
+    pub fn from_record_vec(rec_vec: Vec<GffRecord>) -> GffData {
+        GffData{ data: rec_vec }
+    }
+
+    /// Read a GFF3 or GTF file. Comment lines are skipped, and reading stops
+    /// at an embedded FASTA section. The format is detected from the attributes
+    /// of the first feature.
+    pub fn from_file(fname: &path::PathBuf) -> Result<GffData, Box<dyn Error>> {
 
         let file = File::open(fname).unwrap_or_else(|err| {
-            eprintln!("Problem reading bedgraph file {:?}: {}", fname, err);
+            eprintln!("Problem reading GFF file {:?}: {}", fname, err);
             process::exit(1);
         });
-        // open buffered reader to bedgraph file
+        // open buffered reader to GFF file
         let buf_reader = BufReader::new(file);
 
         let mut rdr = csv::ReaderBuilder::new()
             .delimiter(b'\t')
             .has_headers(false)
+            .comment(Some(b'#'))
+            .quoting(false)
+            .flexible(true)
             .from_reader(buf_reader);
-        let mut records: Vec<BEDGraphRecord> = Vec::new();
+        let mut records: Vec<GffRecord> = Vec::new();
+        let mut format: Option<GffFormat> = None;
 
-        for result in rdr.deserialize() {
-            let record = result.unwrap_or_else(|err| {
-                eprintln!("Problem with your bedgraph records. Is {:?} a properly-formed bedgraph file?: {}", fname, err);
+        for result in rdr.records() {
+            let row = result.and_then(|row| {
+                if row.len() == 1 && row[0].starts_with('>') {
+                    Ok(None)
+                } else {
+                    row.deserialize::<GffRow>(None).map(Some)
+                }
+            }).unwrap_or_else(|err| {
+                eprintln!("Problem with your GFF records. Is {:?} a properly-formed GFF or GTF file?: {}", fname, err);
                 process::exit(1);
             });
-            records.push(record);
+            let row = match row {
+                Some(row) => row,
+                None => break,
+            };
+            if row.start == 0 {
+                eprintln!("Problem with your GFF records. Is {:?} a properly-formed GFF or GTF file?: feature at {}:0 has a start of 0, but GFF coordinates are one-based", fname, row.seqname);
+                process::exit(1);
+            }
+            let format = *format.get_or_insert_with(|| GffFormat::detect(&row.attributes));
+            records.push(GffRecord {
+                seqname: row.seqname,
+                source: row.source,
+                feature_type: row.feature_type,
+                start: row.start - 1,
+                end: row.end,
+                score: row.score,
+                strand: row.strand,
+                phase: row.phase.parse::<u8>().ok(),
+                attributes: parse_gff_attributes(&row.attributes, format),
+            });
         }
-        Ok(BEDGraphData{ data: records })
+        Ok(GffData{ data: records })
     }
 
-    /// Writes the bedgraph data to stdout
-    pub fn print(&self) -> Result<(), Box<dyn Error>> {
-        let mut stdout = stdout();
-        for record in &self.data {
-            write!(stdout, "{}", record)?;
+    /// Returns number of records in self
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if self contains no records
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterate over the records in self
+    pub fn iter(&self) -> std::slice::Iter<'_, GffRecord> {
+        self.data.iter()
+    }
+
+    /// returns the id of the gene to which the record at index i belongs. Genes are
+    /// identified by their ID attribute in GFF3 files, which other features reach
+    /// through their Parent attributes, or by the gene_id attribute in GTF files.
+    fn gene_key(&self, i: usize, id_index: &HashMap<&str, usize>) -> Option<String> {
+        let mut record = &self.data[i];
+        // a feature is rarely more than a few levels below its gene
+        for _ in 0..10 {
+            if GENE_TYPES.contains(&record.feature_type.as_str()) {
+                return record.attribute("ID")
+                    .or_else(|| record.attribute("gene_id"))
+                    .map(String::from);
+            }
+            if let Some(gene_id) = record.attribute("gene_id") {
+                return Some(gene_id.to_string());
+            }
+            let parent = record.attribute("Parent")?.split(',').next()?;
+            record = &self.data[*id_index.get(parent)?];
         }
-        Ok(())
+        None
     }
 
-    /// Writes the bedgraph data to a bedgraph file
-    pub fn to_file(&self, fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
-        let outf = File::create(fname)?;
-        let mut writer = BufWriter::new(outf);
+    /// groups the features of self into gene models, in order of each gene's first
+    /// appearance. Features belonging to no gene are ignored. Genes without a gene
+    /// feature, as is usual in GTF files, span all of their features.
+    pub fn gene_models(&self) -> Vec<GeneModel> {
+        let id_index: HashMap<&str, usize> = self.iter()
+            .enumerate()
+            .filter_map(|(i,x)| x.attribute("ID").map(|id| (id, i)))
+            .collect();
+        let mut genes: Vec<GeneModel> = Vec::new();
+        let mut gene_index: HashMap<String, usize> = HashMap::new();
+        let mut has_gene_feature: Vec<bool> = Vec::new();
 
-        for record in &self.data {
-            writeln!(writer, "{}", record)?;
+        for (i,record) in self.iter().enumerate() {
+            let key = match self.gene_key(i, &id_index) {
+                Some(key) => key,
+                None => continue,
+            };
+            let g = *gene_index.entry(key.to_string()).or_insert_with(|| {
+                genes.push(GeneModel {
+                    id: key.to_string(),
+                    name: key.to_string(),
+                    seqname: record.seqname.to_string(),
+                    start: record.start,
+                    end: record.end,
+                    strand: record.strand,
+                    cds: Vec::new(),
+                    exons: Vec::new(),
+                    utr5: Vec::new(),
+                    utr3: Vec::new(),
+                });
+                has_gene_feature.push(false);
+                genes.len() - 1
+            });
+            let gene = &mut genes[g];
+            let interval = (record.start, record.end);
+            if GENE_TYPES.contains(&record.feature_type.as_str()) {
+                gene.start = record.start;
+                gene.end = record.end;
+                gene.strand = record.strand;
+                has_gene_feature[g] = true;
+            } else if !has_gene_feature[g] {
+                gene.start = gene.start.min(record.start);
+                gene.end = gene.end.max(record.end);
+            }
+            if let Some(name) = ["Name", "gene_name", "gene"].iter().find_map(|x| record.attribute(x)) {
+                if gene.name == gene.id {
+                    gene.name = name.to_string();
+                }
+            }
+            match record.feature_type.as_str() {
+                "CDS" => gene.cds.push(interval),
+                "exon" => gene.exons.push(interval),
+                "five_prime_UTR" | "five_prime_utr" | "5UTR" => gene.utr5.push(interval),
+                "three_prime_UTR" | "three_prime_utr" | "3UTR" => gene.utr3.push(interval),
+                _ => (),
+            }
         }
-        Ok(())
+        genes
     }
+}
 
-    /// Returns number of records in self
-    pub fn len(&self) -> usize {
-        self.data.len()
+/// enables slicing of GffData struct
+impl<Idx> std::ops::Index<Idx> for GffData
+where
+    Idx: std::slice::SliceIndex<[GffRecord]>,
+{
+    type Output = Idx::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
     }
+}
 
-    /// Iterate over each record in the database as a [StrandedSequence] value pair
-    fn iter(&self) -> BEDGraphDataIter{
-        BEDGraphDataIter { loc: 0, bgd: &self, size: self.len() }
+/// context of a position relative to gene models, as assigned by
+/// [BEDData::annotate_regions]. Where contexts from several genes apply,
+/// the first listed here is used.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum GenomicContext {
+    /// within the promoter window around a transcription start site
+    Promoter,
+    FivePrimeUtr,
+    ThreePrimeUtr,
+    Cds,
+    /// within a gene with no CDS, such as a tRNA or rRNA gene
+    NonCoding,
+    Intron,
+    Intergenic,
+}
+
+/// Implement `Display` for `GenomicContext`.
+impl fmt::Display for GenomicContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            GenomicContext::Promoter => "promoter",
+            GenomicContext::FivePrimeUtr => "5UTR",
+            GenomicContext::ThreePrimeUtr => "3UTR",
+            GenomicContext::Cds => "CDS",
+            GenomicContext::NonCoding => "noncoding",
+            GenomicContext::Intron => "intron",
+            GenomicContext::Intergenic => "intergenic",
+        };
+        write!(f, "{}", label)
     }
-    
-    /// filters records in self to those within the given range
-    /// returns a new BEDGraphData instance
-    pub fn filter(
-            &self,
-            seqname: &str,
-            start: &usize,
-            end: &usize,
-    ) -> Result<BEDGraphData, Box<dyn Error>> {
+}
 
-        let records: Vec::<BEDGraphRecord> = self.iter()
-            .filter(|x| {
-                x.seqname == seqname
-                && x.start >= *start
-                && x.end <= *end
-            }).collect();
-        
-        Ok(BEDGraphData{data: records})
+/// a gene and its CDS, exon, and UTR intervals, as built by [GffData::gene_models]
+#[derive(Debug, PartialEq, Clone)]
+pub struct GeneModel {
+    id: String,
+    name: String,
+    seqname: String,
+    start: usize,
+    end: usize,
+    strand: Strand,
+    cds: Vec<(usize, usize)>,
+    exons: Vec<(usize, usize)>,
+    utr5: Vec<(usize, usize)>,
+    utr3: Vec<(usize, usize)>,
+}
+
+impl GeneModel {
+    pub fn id(&self) -> &str {
+        &self.id
     }
 
-    fn get_cpm(&self) -> Result<Vec<f64>, Box<dyn Error>> {
-        let scores = self.fetch_scores()?;
-        let sum: f64 = scores.iter().sum();
-        let cpm: Vec<f64> = scores.iter()
-            .map(|a| a / sum * 1_000_000.0)
-            .collect();
-        Ok(cpm)
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
-    pub fn to_cpm(&mut self) -> Result<(), Box<dyn Error>> {
-        let cpm = self.get_cpm()?;
-        for (i,new_score) in cpm.iter().enumerate() {
-            self.data[i].set_score(*new_score);
-        }
-        Ok(())
+    pub fn seqname(&self) -> &str {
+        &self.seqname
     }
 
-    /// returns a Result, which if successful, contains the score
-    /// column of the bedgraph file as a vector of f64 values
-    pub fn fetch_scores(&self) -> Result<Vec<f64>, Box<dyn Error>> {
-        let scores: Vec::<f64> = self.iter()
-            .map(|record| record.score)
-            .collect();
-        Ok(scores)
+    pub fn start(&self) -> usize {
+        self.start
     }
 
-    /// returns a vec of contig names
-    fn get_contigs(&self) -> Vec<String> {
-        let contigs = self.iter()
-            .map(|x| x.seqname)
-            .unique()
-            .collect();
-        contigs
+    pub fn end(&self) -> usize {
+        self.end
     }
 
-    fn get_max_end(&self) -> usize {
-        self.iter().map(|x| x.end).max().unwrap()
+    pub fn strand(&self) -> Strand {
+        self.strand
     }
 
-    /// returns the greatest end position in self
-    fn get_contig_length(&self, seqname: &str) -> Result<usize, Box<dyn Error>> {
-        let ctg_bg = self.filter(
-            seqname,
-            &0,
-            &usize::MAX,
-        )?;
-        Ok(ctg_bg.get_max_end())
+    /// position of the transcription start site: the first base of the
+    /// gene, or its last base if on the reverse strand
+    pub fn tss(&self) -> usize {
+        if self.strand == Strand::Reverse { self.end - 1 } else { self.start }
     }
 
-    fn get_padded_scores(
-            &self,
-            pad_size: usize,
-            circular: bool,
-    ) -> Result<Vec<f64>, Box<dyn Error>> {
-        let scores = self.fetch_scores()?;
-        let n_scores = scores.len();
-        let mut padded: Vec<f64> = Vec::with_capacity(n_scores + pad_size * 2);
-        if circular {
-            padded.extend_from_slice(&scores[n_scores-pad_size..]);
-            padded.extend_from_slice(&scores[..]);
-            padded.extend_from_slice(&scores[..pad_size]);
+    /// context of position within self, which must lie within the gene. Without
+    /// UTR features, positions outside the CDS and within exons, or within the gene
+    /// if it has no exon features, are assigned to the UTRs.
+    fn classify(&self, position: usize) -> GenomicContext {
+        let within = |intervals: &[(usize, usize)]| {
+            intervals.iter().any(|(start, end)| (*start..*end).contains(&position))
+        };
+        let reverse = self.strand == Strand::Reverse;
+        if within(&self.utr5) {
+            GenomicContext::FivePrimeUtr
+        } else if within(&self.utr3) {
+            GenomicContext::ThreePrimeUtr
+        } else if within(&self.cds) {
+            GenomicContext::Cds
+        } else if !self.exons.is_empty() && !within(&self.exons) {
+            GenomicContext::Intron
+        } else if self.cds.is_empty() {
+            GenomicContext::NonCoding
+        } else if position < self.cds.iter().map(|x| x.0).min().unwrap() {
+            if reverse { GenomicContext::ThreePrimeUtr } else { GenomicContext::FivePrimeUtr }
+        } else if position >= self.cds.iter().map(|x| x.1).max().unwrap() {
+            if reverse { GenomicContext::FivePrimeUtr } else { GenomicContext::ThreePrimeUtr }
         } else {
-            for _ in 0..pad_size {
-                padded.push(scores[0]);
-            }
-            padded.extend_from_slice(&scores[..]);
-            for _ in 0..pad_size {
-                padded.push(scores[n_scores-1]);
-            }
+            GenomicContext::Intron
         }
-        Ok(padded)
     }
-    
-    fn median(&self) -> Result<f64, Box<dyn Error>> {
-        let mut scores = self.fetch_scores()?;
-        let med = median(&mut scores)?;
-        Ok(med)
+}
+
+/// parameters of [BEDData::annotate_regions]
+#[derive(Debug, PartialEq, Clone)]
+pub struct AnnotationParams {
+    /// bp upstream of a transcription start site within its promoter
+    pub promoter_upstream: usize,
+    /// bp downstream of a transcription start site within its promoter
+    pub promoter_downstream: usize,
+    /// if given, consecutive genes on the same strand separated by no more than this
+    /// many bp are grouped into operons, which are transcribed from the promoter of
+    /// their first gene. Nearest genes and promoters are then found only at the starts
+    /// of operons, and each region is labelled with all genes in its nearest operon.
+    pub operon_gap: Option<usize>,
+}
+
+impl Default for AnnotationParams {
+    fn default() -> AnnotationParams {
+        AnnotationParams {
+            promoter_upstream: 500,
+            promoter_downstream: 100,
+            operon_gap: None,
+        }
     }
+}
 
-    fn mean(&self) -> Result<f64, Box<dyn Error>> {
-        let scores = self.fetch_scores()?;
-        let mean_score = mean(&scores)?;
-        Ok(mean_score)
+/// a region labelled with its nearest gene and genomic context,
+/// as returned by [BEDData::annotate_regions]
+#[derive(Debug, PartialEq, Clone)]
+pub struct AnnotatedRegion {
+    region: BEDRecord,
+    position: usize,
+    context: GenomicContext,
+    gene: Option<GeneModel>,
+    tss_distance: Option<i64>,
+    operon: Vec<String>,
+}
+
+impl AnnotatedRegion {
+    pub fn region(&self) -> &BEDRecord {
+        &self.region
     }
 
-    fn mad(&self) -> Result<f64, Box<dyn Error>> {
-        let mut scores = self.fetch_scores()?;
-        let score_mad = mad(&mut scores)?;
-        Ok(score_mad)
+    /// position within the region that was annotated: its midpoint, or a peak's summit
+    pub fn position(&self) -> usize {
+        self.position
     }
 
-    pub fn get_resolution(&self) -> usize {
-        self.data[1].start - self.data[0].start
+    pub fn context(&self) -> GenomicContext {
+        self.context
     }
 
-    /// calculates robust z-score across entire genome for each position
-    pub fn robust_z(
-            &self,
-    ) -> Result<BEDGraphData, Box<dyn Error>> {
+    /// the gene whose transcription start site is nearest, or with operons, the first
+    /// gene of the nearest operon. None if no gene lies on the region's contig.
+    pub fn gene(&self) -> Option<&GeneModel> {
+        self.gene.as_ref()
+    }
 
-        let score_mad = self.mad()?;
-        let score_median = self.median()?;
-        let records: Vec::<BEDGraphRecord> = self.iter()
-            .map(|x| {
-                BEDGraphRecord {
-                    seqname: x.seqname,
-                    start: x.start,
-                    end: x.end,
-                    score: robust_z(x.score, score_median, score_mad),
-                }
-            }).collect();
-        
-        Ok(BEDGraphData{data: records})
+    /// distance from the nearest transcription start site to the annotated position,
+    /// negative if upstream in the gene's direction of transcription
+    pub fn tss_distance(&self) -> Option<i64> {
+        self.tss_distance
     }
 
-    /// calculats rolling function for each contig in the bedgraph file
-    pub fn roll_fn(
-            &self,
-            window_size: usize,
-            circular: bool,
-            func: RollFn,
-    ) -> Result<BEDGraphData, Box<dyn Error>> {
-        if window_size % 2 == 0 {
-            eprintln!("Window size should be an odd number for rolling mean, but you entered {}. Exiting now.", window_size);
-            process::exit(1);
-        }
-        let win_size_f = window_size as f64;
-        let contigs = self.get_contigs();
-        let mut records: Vec::<BEDGraphRecord> = Vec::with_capacity(self.len());
-        for contig in contigs {
-            let contig_bg = self.filter(
-                &contig,
-                &0,
-                &usize::MAX,
-            )?;
-            let padded_scores = contig_bg.get_padded_scores(
-                (window_size-1)/2,
-                circular,
-            )?;
+    /// names of the genes in the nearest operon, in order of transcription,
+    /// or of the nearest gene alone if operons are not used
+    pub fn operon(&self) -> &[String] {
+        &self.operon
+    }
+}
 
-            let mut results: Vec<f64> = Vec::with_capacity(contig_bg.len());
-            if let RollFn::Median = func {
-                for window in padded_scores.windows(window_size) {
-                    let mut win: Vec<f64> = window.to_vec();
-                    win.sort_by(|a, b| a.partial_cmp(b).unwrap());
-                    let median = win[window_size / 2];
-                    results.push(median);
-                }
-            } else if let RollFn::Mean = func {
-                let mut mean_prev_opt: Option<(f64, f64)> = None;
+/// Implement `Display` for `AnnotatedRegion`, as the six columns of a bed record followed
+/// by the context, nearest gene id, name, and strand, distance to its transcription start
+/// site, and comma-separated operon members. Missing values are written as ".".
+impl fmt::Display for AnnotatedRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let score = match self.region.score {
+            Some(score) => score.to_string(),
+            None => String::from("."),
+        };
+        let (gene_id, gene_name, gene_strand) = match &self.gene {
+            Some(gene) => (gene.id.as_str(), gene.name.as_str(), gene.strand),
+            None => (".", ".", Strand::Unknown),
+        };
+        let distance = match self.tss_distance {
+            Some(distance) => distance.to_string(),
+            None => String::from("."),
+        };
+        let operon = if self.operon.is_empty() { String::from(".") } else { self.operon.join(",") };
+        writeln!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.region.seqname,
+            self.region.start,
+            self.region.end,
+            self.region.name.as_deref().unwrap_or("."),
+            score,
+            self.region.strand(),
+            self.context,
+            gene_id,
+            gene_name,
+            gene_strand,
+            distance,
+            operon,
+        )
+    }
+}
 
-                for window in padded_scores.windows(window_size) {
-                    let mean = match mean_prev_opt {
-                        None => {
-                            window.iter().sum::<f64>() / win_size_f
-                        },
-                        Some((prev_mean, prev)) => {
-                            let next = window.last().unwrap();
-                            let mean = prev_mean + (*next - prev) / win_size_f;
-                            mean
-                        }
-                    };
-                    let prev = window.first().unwrap();
-                    results.push(mean);
-                    mean_prev_opt = Some((mean, *prev))
-                }
-            } else {
-                println!("No valid RollFn enum present. Exiting now.");
-                process::exit(1);
-            }
-            for (i,result) in results.iter().enumerate() {
-                let record = BEDGraphRecord::new(
-                    contig.to_string(),
-                    contig_bg.data[i].start,
-                    contig_bg.data[i].end,
-                    *result,
-                );
-                records.push( record );
-            }
+/// holds regions annotated with genes, as returned by [BEDData::annotate_regions]
+pub struct AnnotationData {
+    data: Vec<AnnotatedRegion>,
+}
+
+impl AnnotationData {
+    /// Writes the annotated regions to stdout
+    pub fn print(&self) -> Result<(), Box<dyn Error>> {
+        let mut stdout = stdout();
+        for record in &self.data {
+            write!(stdout, "{}", record)?;
         }
-        Ok(BEDGraphData{data: records})
+        Ok(())
     }
 
-    pub fn unify_bins(
-            &self,
-            width: usize,
-    ) -> Result<BEDGraphData, Box<dyn Error>> {
-
-        let contigs = self.get_contigs();
-        let mut records: Vec::<BEDGraphRecord> = Vec::with_capacity(self.len());
+    /// Writes the annotated regions to an extended bed file
+    pub fn to_file(&self, fname: &path::PathBuf) -> Result<(), Box<dyn Error>> {
+        let outf = File::create(fname)?;
+        let mut writer = BufWriter::new(outf);
 
-        for contig in contigs {
-            let contig_bg = self.filter(
-                &contig,
-                &0,
-                &usize::MAX,
-            )?;
-            let final_end = contig_bg.get_max_end();
-            for orig_record in contig_bg.iter() {
-                let mut new_records = orig_record.split_evenly(width, final_end)?;
-                records.append(&mut new_records)
-            }
+        for record in &self.data {
+            write!(writer, "{}", record)?;
         }
+        Ok(())
+    }
 
-        Ok(BEDGraphData{data: records})
+    /// Returns number of records in self
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if self contains no records
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Iterate over the records in self
+    pub fn iter(&self) -> std::slice::Iter<'_, AnnotatedRegion> {
+        self.data.iter()
     }
 }
 
-/// enables slicing of BEDGraphData struct
-impl<Idx> std::ops::Index<Idx> for BEDGraphData
+/// enables slicing of AnnotationData struct
+impl<Idx> std::ops::Index<Idx> for AnnotationData
 where
-    Idx: std::slice::SliceIndex<[BEDGraphRecord]>,
+    Idx: std::slice::SliceIndex<[AnnotatedRegion]>,
 {
     type Output = Idx::Output;
 
@@ -1041,37 +8285,123 @@ where
     }
 }
 
-/// Allows for iteration over a BEDGraphData struct
-///
-/// # Fields
-///
-/// * `loc` - Current location in the database
-/// * `bgd` - A reference to the [BEDGraphData]
-/// * `size` - The number of BEDGraphRecords in bgd
-pub struct BEDGraphDataIter<'a> {
-    loc: usize,
-    bgd: &'a BEDGraphData,
-    size: usize,
+/// genes transcribed together from the promoter of the first, as indices of gene models
+struct TranscriptionUnit {
+    genes: Vec<usize>,
+    tss: usize,
 }
 
-/// Enables iteration over the BEDGraphData. Returns a [BEDGraphRecord] as 
-/// each item.
-impl<'a> Iterator for BEDGraphDataIter<'a> {
-    type Item = BEDGraphRecord;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.loc == self.size{
-            None
-        } else {
-            let out_rec = &self.bgd[self.loc];
-            self.loc += 1;
-            Some(BEDGraphRecord::new(
-                out_rec.seqname.to_string(),
-                out_rec.start,
-                out_rec.end,
-                out_rec.score,
-            ))
+/// annotates each region at the paired position. See [BEDData::annotate_regions].
+fn annotate_positions(
+        regions: Vec<(BEDRecord, usize)>,
+        genes: &[GeneModel],
+        params: &AnnotationParams,
+) -> AnnotationData {
+    // group genes into transcription units, per contig, sorted by start site
+    let mut by_strand: HashMap<(&str, bool), Vec<usize>> = HashMap::new();
+    for (i,gene) in genes.iter().enumerate() {
+        by_strand.entry((&gene.seqname, gene.strand == Strand::Reverse))
+            .or_default()
+            .push(i);
+    }
+    let mut units: HashMap<&str, Vec<TranscriptionUnit>> = HashMap::new();
+    for ((seqname, reverse), mut members) in by_strand {
+        members.sort_by_key(|i| genes[*i].start);
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group_end = 0;
+        for i in members {
+            let joins = match (params.operon_gap, groups.last()) {
+                (Some(gap), Some(_)) => genes[i].start <= group_end + gap,
+                _ => false,
+            };
+            if joins {
+                groups.last_mut().unwrap().push(i);
+                group_end = group_end.max(genes[i].end);
+            } else {
+                groups.push(vec![i]);
+                group_end = genes[i].end;
+            }
+        }
+        for mut group in groups {
+            if reverse {
+                group.reverse();
+            }
+            let tss = genes[group[0]].tss();
+            units.entry(seqname).or_default().push(TranscriptionUnit{genes: group, tss});
         }
     }
-}
+    for contig_units in units.values_mut() {
+        contig_units.sort_by_key(|x| x.tss);
+    }
+
+    let mut by_contig: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i,gene) in genes.iter().enumerate() {
+        by_contig.entry(&gene.seqname).or_default().push(i);
+    }
+    for idxs in by_contig.values_mut() {
+        idxs.sort_by_key(|i| genes[*i].start);
+    }
+    let max_length = genes.iter().map(|x| x.end - x.start).max().unwrap_or(0);
+    let reach = params.promoter_upstream.max(params.promoter_downstream) + 1;
 
+    let records: Vec<AnnotatedRegion> = regions.into_iter().map(|(region, position)| {
+        let mut context = GenomicContext::Intergenic;
+        if let Some(idxs) = by_contig.get(region.seqname.as_str()) {
+            let first = idxs.partition_point(|i| genes[*i].start + max_length <= position);
+            for i in &idxs[first..] {
+                let gene = &genes[*i];
+                if gene.start > position {
+                    break;
+                }
+                if position < gene.end {
+                    context = context.min(gene.classify(position));
+                }
+            }
+        }
+
+        let mut nearest: Option<(&TranscriptionUnit, i64)> = None;
+        if let Some(contig_units) = units.get(region.seqname.as_str()) {
+            // units with start sites close enough to hold position in their promoters
+            let lo = contig_units.partition_point(|x| x.tss + reach <= position);
+            let hi = contig_units.partition_point(|x| x.tss < position + reach);
+            for unit in &contig_units[lo..hi] {
+                let (upstream, downstream) = if genes[unit.genes[0]].strand == Strand::Reverse {
+                    (unit.tss + params.promoter_upstream, unit.tss.saturating_sub(params.promoter_downstream))
+                } else {
+                    (unit.tss.saturating_sub(params.promoter_upstream), unit.tss + params.promoter_downstream)
+                };
+                let in_promoter = if genes[unit.genes[0]].strand == Strand::Reverse {
+                    position > downstream && position <= upstream
+                } else {
+                    position >= upstream && position < downstream
+                };
+                if in_promoter {
+                    context = context.min(GenomicContext::Promoter);
+                }
+            }
+            let i = contig_units.partition_point(|x| x.tss < position);
+            for unit in contig_units[i.saturating_sub(1)..(i + 1).min(contig_units.len())].iter() {
+                let distance = if genes[unit.genes[0]].strand == Strand::Reverse {
+                    unit.tss as i64 - position as i64
+                } else {
+                    position as i64 - unit.tss as i64
+                };
+                if nearest.is_none_or(|x| distance.abs() < x.1.abs()) {
+                    nearest = Some((unit, distance));
+                }
+            }
+        }
+
+        AnnotatedRegion {
+            region,
+            position,
+            context,
+            gene: nearest.map(|x| genes[x.0.genes[0]].clone()),
+            tss_distance: nearest.map(|x| x.1),
+            operon: nearest.map_or(Vec::new(), |x| {
+                x.0.genes.iter().map(|i| genes[*i].name.to_string()).collect()
+            }),
+        }
+    }).collect();
+    AnnotationData{data: records}
+}